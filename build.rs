@@ -1,5 +1,6 @@
 #![feature(try_trait)]
 
+use std::path::PathBuf;
 use which::which;
 
 fn eat(command: &mut std::process::Command) {
@@ -10,7 +11,9 @@ fn eat(command: &mut std::process::Command) {
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// Builds DuckDB and our reexporter with emcc, returning the sysroot its
+/// headers are in
+fn build_emscripten() -> Result<PathBuf, Box<dyn std::error::Error>> {
     // TODO: reenable
     // println!("cargo:rustc-link-lib=static-nobundle=stdc++");
 
@@ -38,6 +41,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .arg("-fvisibility=default")
         .arg("-fPIC")
         .arg("-DDUCKDB_NO_THREADS=1")
+        // DuckDB's own asserts are for its debug builds, and abort the process
+        .arg("-DNDEBUG")
         .arg("-sWASM=1")
         .arg("-DDUCKDB_BUILD_LIBRARY=1")
         .arg("-sWARN_ON_UNDEFINED_SYMBOLS=1")
@@ -48,10 +53,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .arg("-Wno-unused-parameter")
         .arg("--no-entry")
         .arg("-shared")
+        // includes target/duckdb.cpp
         .arg("src/reexporter.cpp")
         .arg("-Itarget")
-        // .arg("target/duckdb.wasm")
-        .arg("target/duckdb.cpp")
         .arg("-o")
         .arg("duckdb.o"));
 
@@ -78,16 +82,42 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::env::set_var("LIBCLANG_PATH", &p);
     }
 
-    bindgen::builder()
-        .header("target/duckdb.h")
+    Ok(emar_path.join("../cache/sysroot/include"))
+}
+
+/// Builds the same amalgamation, which `src/reexporter.cpp` includes, with
+/// the host's C++ compiler and threads enabled
+fn build_native() {
+    cc::Build::new()
+        .cpp(true)
+        .flag_if_supported("-std=c++11")
+        .flag_if_supported("-Wno-unused-parameter")
+        .define("DUCKDB_BUILD_LIBRARY", "1")
+        .define("NDEBUG", None)
+        .include("target")
+        .file("src/reexporter.cpp")
+        .warnings(false)
+        .compile("duckdb");
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=src/reexporter.cpp");
+    println!("cargo:rerun-if-changed=target/duckdb.cpp");
+
+    let sysroot = if std::env::var("CARGO_CFG_TARGET_OS")? == "emscripten" {
+        Some(build_emscripten()?)
+    } else {
+        build_native();
+        None
+    };
+
+    let mut builder = bindgen::builder().header("target/duckdb.h");
+    if let Some(sysroot) = sysroot {
+        builder = builder.clang_arg(format!("-I{}", sysroot.to_str().expect("include path")));
+    }
+
+    builder
         // .detect_include_paths(true)
-        .clang_arg(format!(
-            "-I{}",
-            emar_path
-                .join("../cache/sysroot/include")
-                .to_str()
-                .expect("include path")
-        ))
         .generate_block(true)
         .rustified_enum(".*")
         // .clang_arg("-DDUCKDB_BUILD_LIBRARY")
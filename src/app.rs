@@ -0,0 +1,580 @@
+//! The page itself: rendering regions, handling events and running the
+//! queries typed into it. Only the emscripten build has a page to run in
+
+use crate::catalog::Catalog;
+use crate::chart::ChartSpec;
+use crate::data::ResultData;
+use crate::db::Connection;
+use crate::dom::Region;
+use crate::error::{ErrorKind, QueryError};
+use crate::events::{Event, EventKind};
+use crate::history::HistoryEntry;
+use crate::rendering::{
+    ChartView, DatabasePicker, ErrorView, FileBrowser, Form, HistoryPanel, Html, Page, PlanView,
+    Running, Sidebar, StatusBar, Table,
+};
+use crate::view::ResultView;
+use crate::{c_char, ResolvedResult};
+use crate::{
+    catalog, complete, data, databases, dom, events, explain, files, history, jse, persist,
+    promise, routing, sql, worker,
+};
+use render::html;
+use std::cell::{Cell, RefCell};
+use std::ffi::{CStr, CString};
+use std::path::Path;
+use std::thread_local;
+use std::time::{Instant, SystemTime};
+
+pub(crate) fn set_body_html(string: String) -> i32 {
+    jse!(
+        b"document.body.innerHTML = UTF8ToString($0, $1);\x00",
+        &string
+    )
+}
+
+pub(crate) fn set_page_title(string: String) -> i32 {
+    jse!(b"document.title = UTF8ToString($0, $1);\x00", &string)
+}
+thread_local! {
+    static LAST_RESULT: RefCell<Option<Result<Box<dyn ResultData>, QueryError>>> = RefCell::new(None);
+    static PAGE: Cell<Page> = Cell::new(Page::default());
+    static VIEW: RefCell<ResultView> = RefCell::new(ResultView::default());
+    static CHART: RefCell<Option<ChartSpec>> = RefCell::new(None);
+    static RETURNED: RefCell<CString> = RefCell::new(CString::default());
+    /// How many databases are being opened
+    static OPENING: Cell<usize> = Cell::new(0);
+}
+
+const DB_PATH: &str = "db.db";
+
+pub(crate) unsafe fn run_async() -> Result<(), Box<dyn std::error::Error>> {
+    promise::install();
+    persist::install();
+
+    if worker::is_worker() {
+        worker::install_host();
+        promise::spawn(async {
+            open_database(Some(DB_PATH.to_string()))
+                .await
+                .expect("Couldn't open the database");
+            worker::ready();
+        });
+
+        return Ok(());
+    }
+
+    set_page_title("DuckDB Test".to_string());
+
+    events::install();
+    files::install_upload_handler();
+    complete::install();
+
+    dom::mount();
+    dom::patch(Region::Editor, html! { <>{Form {}}</> });
+    render_files(None);
+    render_history();
+
+    if worker::requested() {
+        worker::start();
+        render_sidebar(current_sidebar());
+        start_routing();
+    } else {
+        promise::spawn(async {
+            open_database(Some(DB_PATH.to_string()))
+                .await
+                .expect("Couldn't open the database");
+            render_sidebar(current_sidebar());
+            start_routing();
+        });
+    }
+
+    Ok(())
+}
+
+/// Opens the database at `path`, or an in-memory one, restoring the saved
+/// copy first, and makes it the current one
+async fn open_database(path: Option<String>) -> Result<String, Box<dyn std::error::Error>> {
+    OPENING.with(|opening| opening.set(opening.get() + 1));
+
+    // restoring over a database that's already open would clobber it
+    if let Some(path) = path.as_deref().filter(|path| !databases::is_open(path)) {
+        if let Err(error) = persist::restore(path).await {
+            println!("Couldn't restore the database: {}", error);
+        }
+    }
+
+    let opened = databases::open(path.as_deref());
+    OPENING.with(|opening| opening.set(opening.get() - 1));
+
+    opened
+}
+
+fn start_routing() {
+    routing::install();
+    if let Some(query) = routing::read_hash() {
+        run_linked(&query);
+    }
+}
+
+/// Runs a query from the address. Anyone can send a link, so one that could
+/// change the database only runs if the user agrees, and is otherwise just
+/// put in the editor
+fn run_linked(query: &str) {
+    if sql::is_read_only(query)
+        || dom::confirm(&format!("Run this query from the link?\n\n{}", query))
+    {
+        run_query(query);
+    } else {
+        routing::set_hash(query);
+        dom::set_editor_query(query);
+    }
+}
+
+pub(crate) fn hook(info: &std::panic::PanicInfo) {
+    let mut msg = info.to_string();
+
+    println!("{:?}", msg);
+
+    // Add the error stack to our message.
+    //
+    // This ensures that even if the `console` implementation doesn't
+    // include stacks for `console.error`, the stack is still available
+    // for the user. Additionally, Firefox's console tries to clean up
+    // stack traces, and ruins Rust symbols in the process
+    // (https://bugzilla.mozilla.org/show_bug.cgi?id=1519569) but since
+    // it only touches the logged message's associated stack, and not
+    // the message's contents, by including the stack in the message
+    // contents we make sure it is available to the user.
+    msg.push_str("\n\nStack:\n\n");
+    // #[cfg(not(test))]
+    // {
+    //     let error = js_sys::Error::new("test1");
+    //     println!("{:?}", error);
+    // }
+    // let stack = error.stack();
+    // println!("{:?}", stack);
+    // msg.push_str(stack.as_str().unwrap_or_default());
+
+    // Safari's devtools, on the other hand, _do_ mess with logged
+    // messages' contents, so we attempt to break their heuristics for
+    // doing that by appending some whitespace.
+    // https://github.com/rustwasm/console_error_panic_hook/issues/7
+    msg.push_str("\n\n");
+
+    // Finally, log the panic with `console.error`!
+    println!("{}", msg);
+}
+
+fn sidebar(conn: &Connection) -> Option<Sidebar> {
+    match catalog::cached(conn) {
+        Ok(catalog) => Some(Sidebar { catalog }),
+        Err(error) => {
+            println!("Couldn't load catalog: {}", error);
+            None
+        }
+    }
+}
+
+fn current_sidebar() -> Option<Sidebar> {
+    if worker::enabled() {
+        let catalog = catalog::peek();
+        if catalog.is_none() {
+            promise::spawn(refresh_catalog());
+        }
+
+        return catalog.map(|catalog| Sidebar { catalog });
+    }
+
+    databases::with_connection(sidebar).flatten()
+}
+
+/// Loads the catalog through the worker, then shows it
+async fn refresh_catalog() {
+    match worker::catalog().await {
+        Ok(catalog) => {
+            catalog::store(catalog.clone());
+            render_sidebar(Some(Sidebar { catalog }));
+        }
+        Err(error) => println!("Couldn't load catalog: {}", error),
+    }
+}
+
+fn current_catalog() -> Option<Catalog> {
+    if worker::enabled() {
+        return catalog::peek();
+    }
+
+    databases::with_connection(|conn| catalog::cached(conn).ok()).flatten()
+}
+
+pub(crate) fn render_sidebar(sidebar: Option<Sidebar>) {
+    // the worker has its own databases
+    let picker = if worker::enabled() {
+        None
+    } else {
+        Some(DatabasePicker {
+            names: databases::names(),
+            current: databases::current(),
+            attached: databases::with_connection(databases::attached).unwrap_or_default(),
+        })
+    };
+
+    dom::patch(Region::Sidebar, html! { <>{picker}{sidebar}</> });
+}
+
+pub(crate) fn select_database(name: &str) {
+    databases::select(name);
+
+    render_sidebar(current_sidebar());
+}
+
+/// Opens the file at `path`, or an in-memory database if it's empty
+pub(crate) fn open_database_from(path: &str) {
+    let path = Some(path.trim().to_string()).filter(|path| !path.is_empty());
+
+    promise::spawn(async move {
+        if let Err(error) = open_database(path).await {
+            println!("Couldn't open the database: {}", error);
+        }
+
+        render_sidebar(current_sidebar());
+    });
+}
+
+fn render_files(error: Option<String>) {
+    let error = error.map(|e| html! { <pre><code>{e}</code></pre> });
+    let files = FileBrowser {
+        path: files::current_dir(),
+    };
+
+    dom::patch(Region::Files, html! { <>{files}{error}</> });
+}
+
+fn render_history() {
+    dom::patch(
+        Region::History,
+        html! { <>{HistoryPanel { entries: history::entries() }}</> },
+    );
+}
+
+#[no_mangle]
+extern "C" fn files_dropped(count: u32) {
+    promise::spawn(async move {
+        let error = files::save_uploads(count).await.err();
+
+        render_files(error.map(|e| e.to_string()));
+    });
+}
+
+pub(crate) fn browse(path: &Path) {
+    files::set_current_dir(path.to_path_buf());
+    render_files(None);
+}
+
+pub(crate) fn delete_file(path: &Path) {
+    render_files(files::remove(path).err().map(|e| e.to_string()));
+}
+
+pub(crate) fn rename_file(from: &Path, to: &str) {
+    render_files(files::rename(from, to).err().map(|e| e.to_string()));
+}
+
+#[no_mangle]
+pub(crate) extern "C" fn callback(query_: *const c_char) {
+    let org = unsafe { CStr::from_ptr(query_) };
+    let query = org.to_string_lossy();
+
+    println!("you called?: {} {:?} {:?}", query, org, query_);
+
+    run_query(&query);
+}
+
+#[no_mangle]
+extern "C" fn hash_changed() {
+    if let Some(query) = routing::navigated_to() {
+        run_linked(&query);
+    }
+}
+
+pub(crate) fn run_query(query: &str) {
+    routing::set_hash(query);
+    dom::set_editor_query(query);
+
+    let started = Instant::now();
+
+    if worker::enabled() {
+        dom::patch(Region::Status, html! { <>{Running {}}</> });
+
+        let query = query.to_string();
+        promise::spawn(async move {
+            let result = worker::query(&query).await;
+
+            show_result(
+                &query,
+                started,
+                result.map(|r| Box::new(r) as Box<dyn ResultData>),
+            );
+        });
+
+        return;
+    }
+
+    let result = execute(query);
+    show_result(
+        query,
+        started,
+        result.map(|r| Box::new(r) as Box<dyn ResultData>),
+    );
+}
+
+/// Runs `query` on the current database, saving it if the query changed it
+fn execute(query: &str) -> Result<ResolvedResult<'static>, QueryError> {
+    let result = databases::with_connection(|conn| conn.query(query)).unwrap_or_else(|| {
+        // the page is up before the database has been opened, and stays up
+        // if opening it fails
+        let message = match OPENING.with(Cell::get) {
+            0 => "No database is open",
+            _ => "The database is still opening",
+        };
+
+        Err(QueryError::new(query, ErrorKind::Unknown, message))
+    });
+
+    if result.is_ok() && (sql::is_dml(query) || catalog::changes_catalog(query)) {
+        if let Some(path) = databases::current_path() {
+            promise::spawn(async move {
+                if let Err(error) = persist::save(&path).await {
+                    println!("Couldn't save the database: {}", error);
+                }
+            });
+        }
+    }
+
+    result
+}
+
+/// Records a finished query and shows its result, wherever it ran
+fn show_result(query: &str, started: Instant, result: Result<Box<dyn ResultData>, QueryError>) {
+    history::record(HistoryEntry {
+        query: query.to_string(),
+        timestamp: SystemTime::now(),
+        duration: result
+            .as_ref()
+            .map_or_else(|_| started.elapsed(), |r| r.execution_time()),
+        success: result.is_ok(),
+    });
+
+    if catalog::changes_catalog(query) {
+        catalog::invalidate();
+    }
+
+    LAST_RESULT.with(|last| last.replace(Some(result)));
+    VIEW.with(|view| view.replace(ResultView::default()));
+    CHART.with(|chart| chart.replace(None));
+    first_page();
+
+    render_sidebar(current_sidebar());
+    render_history();
+    render_results();
+}
+
+/// Stops the query running in the worker. Without a worker there's nothing
+/// to cancel: queries run on the page's only thread, which doesn't handle
+/// clicks until they finish, and the editor says so
+pub(crate) fn cancel_query() {
+    if worker::enabled() {
+        worker::cancel();
+    }
+}
+
+/// Interrupts whatever this worker is running on the current database
+#[no_mangle]
+extern "C" fn worker_interrupt() {
+    databases::with_connection(|conn| conn.interrupt_handle().interrupt());
+}
+
+/// Runs a query the page sent to this worker, and posts back the result
+#[no_mangle]
+extern "C" fn worker_query(id: i32, query: *const c_char) {
+    let query = unsafe { CStr::from_ptr(query) }.to_string_lossy();
+
+    let result = execute(&query);
+    let reply =
+        data::encode(result.as_ref().map(|r| r as &dyn ResultData)).unwrap_or_else(|error| {
+            let error = QueryError::new(&query, ErrorKind::Unknown, &error.to_string());
+
+            data::encode(Err(&error)).expect("error reply")
+        });
+
+    worker::reply(id, &reply);
+}
+
+/// Renders the last result, or its error, along with its status line
+fn render_results() {
+    let (status, results) = LAST_RESULT.with(|last| match &*last.borrow() {
+        Some(Ok(data)) => {
+            let data: &dyn ResultData = &**data;
+            let page = PAGE.with(|page| page.get());
+
+            let plan = if explain::is_explain(data) {
+                explain::from_result(data)
+                    .ok()
+                    .map(|plans| PlanView { plans })
+            } else {
+                None
+            };
+            let rendering = Instant::now();
+            let results = VIEW.with(|view| {
+                let view = view.borrow();
+                let rows = match plan {
+                    Some(_) => None,
+                    None => Some(view.rows(data)),
+                };
+                let (table, chart, failed) = match &rows {
+                    None => (None, None, None),
+                    Some(Ok(rows)) => (
+                        Some(Table {
+                            data,
+                            page,
+                            view: &view,
+                            rows,
+                        }),
+                        Some(ChartView {
+                            data,
+                            rows,
+                            spec: CHART.with(|chart| chart.borrow().clone()),
+                        }),
+                        None,
+                    ),
+                    Some(Err(error)) => (
+                        None,
+                        None,
+                        Some(Html(html! { <pre><code>{error.to_string()}</code></pre> })),
+                    ),
+                };
+
+                html! { <>{plan}{table}{chart}{failed}</> }
+            });
+            let status = StatusBar {
+                rows: data.row_count(),
+                rows_affected: data.rows_affected(),
+                execution: data.execution_time(),
+                rendering: rendering.elapsed(),
+            };
+
+            (html! { <>{status}</> }, results)
+        }
+        Some(Err(error)) => (
+            String::new(),
+            html! { <>{ErrorView { error: error.clone() }}</> },
+        ),
+        None => (String::new(), String::new()),
+    });
+
+    // the results have the handlers, which the first patch claims
+    dom::patch(Region::Results, results);
+    dom::patch(Region::Status, status);
+}
+
+pub(crate) fn clear_history() {
+    history::clear();
+
+    render_history();
+}
+
+pub(crate) fn goto_page(page: Page) {
+    PAGE.with(|current| {
+        current.set(Page {
+            size: page.size.max(1),
+            ..page
+        })
+    });
+
+    render_results();
+}
+
+fn first_page() {
+    PAGE.with(|page| {
+        page.set(Page {
+            index: 0,
+            ..page.get()
+        })
+    });
+}
+
+pub(crate) fn sort_by(column: u64) {
+    VIEW.with(|view| view.borrow_mut().toggle_sort(column));
+    first_page();
+
+    render_results();
+}
+
+pub(crate) fn chart(spec: Option<ChartSpec>) {
+    CHART.with(|chart| chart.replace(spec));
+
+    render_results();
+}
+
+pub(crate) fn filter_by(column: u64, filter: &str) {
+    VIEW.with(|view| view.borrow_mut().set_filter(column, filter));
+    first_page();
+
+    render_results();
+}
+
+#[no_mangle]
+extern "C" fn settle_promise(id: i32, ok: i32, ptr: *mut u8, len: i32) {
+    let value = unsafe { promise::take_buffer(ptr, len) };
+
+    promise::settle(
+        id,
+        if ok != 0 {
+            Ok(value)
+        } else {
+            Err(String::from_utf8_lossy(&value.unwrap_or_default()).to_string())
+        },
+    );
+}
+
+/// Routes a DOM event to the handler registered under `id`, returning
+/// whether there was one
+#[no_mangle]
+extern "C" fn dispatch(id: *const c_char, kind: *const c_char, payload: *const c_char) -> i32 {
+    let id = unsafe { CStr::from_ptr(id) }.to_string_lossy();
+    let kind = unsafe { CStr::from_ptr(kind) }.to_string_lossy();
+    let payload = unsafe { CStr::from_ptr(payload) }.to_string_lossy();
+
+    let event = match EventKind::from_name(&kind) {
+        Some(kind) => Event {
+            kind,
+            payload: payload.to_string(),
+        },
+        None => return 0,
+    };
+
+    events::dispatch(&id, &event).into()
+}
+
+/// Hands a string back to a `ccall` with a `'string'` return type, valid
+/// until the next one
+fn return_string(string: String) -> *const c_char {
+    let cstring = CString::new(string).expect("string");
+
+    RETURNED.with(|returned| {
+        returned.replace(cstring);
+        returned.borrow().as_ptr()
+    })
+}
+
+/// Completions for the word before the cursor, see `complete::encode`
+#[no_mangle]
+extern "C" fn complete(before: *const c_char, after: *const c_char) -> *const c_char {
+    let before = unsafe { CStr::from_ptr(before) }.to_string_lossy();
+    let after = unsafe { CStr::from_ptr(after) }.to_string_lossy();
+
+    let catalog = current_catalog().unwrap_or_default();
+    let (prefix, completions) = complete::complete(&catalog, &before, &after);
+
+    return_string(complete::encode(prefix, &completions))
+}
@@ -0,0 +1,211 @@
+use crate::data::ResultData;
+use crate::db::Connection;
+use crate::sql::statement_keywords;
+use std::cell::RefCell;
+use std::thread_local;
+
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,
+    pub type_: String,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableKind {
+    Table,
+    View,
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub kind: TableKind,
+    pub columns: Vec<Column>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub name: String,
+    pub tables: Vec<Table>,
+}
+
+pub const TABLES_QUERY: &str = "select table_schema, table_name, table_type from information_schema.tables order by table_schema, table_name";
+pub const COLUMNS_QUERY: &str = "select table_schema, table_name, column_name, data_type, is_nullable from information_schema.columns order by table_schema, table_name, ordinal_position";
+pub const FUNCTIONS_QUERY: &str =
+    "select distinct function_name from duckdb_functions() order by function_name";
+
+/// Used when `duckdb_functions()` isn't available in this DuckDB build
+const FUNCTIONS: &[&str] = &[
+    "abs",
+    "avg",
+    "bit_length",
+    "ceil",
+    "coalesce",
+    "concat",
+    "contains",
+    "count",
+    "current_date",
+    "date_part",
+    "date_trunc",
+    "epoch",
+    "first",
+    "floor",
+    "greatest",
+    "last",
+    "least",
+    "length",
+    "list_value",
+    "lower",
+    "ltrim",
+    "max",
+    "min",
+    "now",
+    "nullif",
+    "random",
+    "read_csv_auto",
+    "read_parquet",
+    "regexp_matches",
+    "regexp_replace",
+    "replace",
+    "round",
+    "rtrim",
+    "sqrt",
+    "stddev_pop",
+    "stddev_samp",
+    "string_agg",
+    "strftime",
+    "strptime",
+    "substring",
+    "sum",
+    "trim",
+    "upper",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    pub schemas: Vec<Schema>,
+    pub functions: Vec<String>,
+}
+impl Catalog {
+    pub fn load(conn: &Connection) -> Result<Self, Box<dyn std::error::Error>> {
+        let tables = conn.query(TABLES_QUERY)?;
+        let columns = conn.query(COLUMNS_QUERY)?;
+        let functions = conn.query(FUNCTIONS_QUERY);
+
+        Self::from_results(
+            &tables,
+            &columns,
+            functions.as_ref().ok().map(|f| f as &dyn ResultData),
+        )
+    }
+
+    /// Builds the catalog from the results of `TABLES_QUERY`, `COLUMNS_QUERY` and, if
+    /// it worked, `FUNCTIONS_QUERY`, wherever they were run
+    pub fn from_results(
+        tables: &dyn ResultData,
+        columns: &dyn ResultData,
+        functions: Option<&dyn ResultData>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut catalog = Catalog::default();
+
+        for row in 0..tables.row_count() {
+            let schema = string(tables, 0, row)?;
+            let kind = if string(tables, 2, row)?.contains("VIEW") {
+                TableKind::View
+            } else {
+                TableKind::Table
+            };
+
+            catalog.schema_mut(&schema).tables.push(Table {
+                name: string(tables, 1, row)?,
+                kind,
+                columns: Vec::new(),
+            });
+        }
+
+        for row in 0..columns.row_count() {
+            let schema = string(columns, 0, row)?;
+            let table = string(columns, 1, row)?;
+
+            if let Some(table) = catalog
+                .schema_mut(&schema)
+                .tables
+                .iter_mut()
+                .find(|t| t.name == table)
+            {
+                table.columns.push(Column {
+                    name: string(columns, 2, row)?,
+                    type_: string(columns, 3, row)?,
+                    nullable: string(columns, 4, row)? == "YES",
+                });
+            }
+        }
+
+        catalog.functions = match functions {
+            Some(functions) => (0..functions.row_count())
+                .map(|row| string(functions, 0, row))
+                .collect::<Result<_, _>>()?,
+            None => FUNCTIONS.iter().map(|name| name.to_string()).collect(),
+        };
+
+        Ok(catalog)
+    }
+
+    fn schema_mut(&mut self, name: &str) -> &mut Schema {
+        match self.schemas.iter().position(|s| s.name == name) {
+            Some(idx) => &mut self.schemas[idx],
+            None => {
+                self.schemas.push(Schema {
+                    name: name.to_string(),
+                    tables: Vec::new(),
+                });
+                self.schemas.last_mut().expect("just pushed")
+            }
+        }
+    }
+}
+
+fn string(data: &dyn ResultData, col: u64, row: u64) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(data.value(col, row)?.to_string())
+}
+
+/// Whether running `query` could change what tables and columns exist
+pub fn changes_catalog(query: &str) -> bool {
+    statement_keywords(query).iter().any(|keyword| {
+        matches!(
+            keyword.as_str(),
+            "create" | "drop" | "alter" | "attach" | "detach" | "import" | "rollback"
+        )
+    })
+}
+
+thread_local! {
+    static CATALOG: RefCell<Option<Catalog>> = RefCell::new(None);
+}
+
+/// The last loaded catalog, loading it first if nothing is cached
+pub fn cached(conn: &Connection) -> Result<Catalog, Box<dyn std::error::Error>> {
+    CATALOG.with(|cache| {
+        if let Some(catalog) = &*cache.borrow() {
+            return Ok(catalog.clone());
+        }
+
+        let catalog = Catalog::load(conn)?;
+        cache.replace(Some(catalog.clone()));
+        Ok(catalog)
+    })
+}
+
+/// The cached catalog, without loading it
+pub fn peek() -> Option<Catalog> {
+    CATALOG.with(|cache| cache.borrow().clone())
+}
+
+pub fn store(catalog: Catalog) {
+    CATALOG.with(|cache| cache.replace(Some(catalog)));
+}
+
+pub fn invalidate() {
+    CATALOG.with(|cache| cache.replace(None));
+}
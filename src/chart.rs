@@ -0,0 +1,312 @@
+use crate::data::ResultData;
+use crate::view::as_number;
+use crate::DbType;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 360.0;
+const LEFT: f64 = 70.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 20.0;
+const BOTTOM: f64 = 50.0;
+const TICKS: usize = 5;
+const COLOURS: [&str; 6] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    Bar,
+    Line,
+    Scatter,
+}
+impl ChartKind {
+    pub const ALL: [ChartKind; 3] = [ChartKind::Bar, ChartKind::Line, ChartKind::Scatter];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChartKind::Bar => "bar",
+            ChartKind::Line => "line",
+            ChartKind::Scatter => "scatter",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+}
+
+/// Which columns to plot, the first against the rest
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub x: u64,
+    pub ys: Vec<u64>,
+}
+
+/// days_from_civil, from http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+/// Places a value on a continuous axis, with dates and times as days
+fn position(value: &DbType) -> Option<f64> {
+    let seconds = |time: &crate::duckdb_time| {
+        f64::from(time.hour) * 3600.0
+            + f64::from(time.min) * 60.0
+            + f64::from(time.sec)
+            + f64::from(time.micros) / 1_000_000.0
+    };
+
+    match value {
+        DbType::Date(date) => {
+            Some(days_from_civil(date.year.into(), date.month.into(), date.day.into()) as f64)
+        }
+        DbType::Timestamp(stamp) => Some(
+            days_from_civil(
+                stamp.date.year.into(),
+                stamp.date.month.into(),
+                stamp.date.day.into(),
+            ) as f64
+                + seconds(&stamp.time) / 86400.0,
+        ),
+        DbType::Time(time) => Some(seconds(time)),
+        value => as_number(value),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+struct Scale {
+    min: f64,
+    max: f64,
+    from: f64,
+    to: f64,
+}
+impl Scale {
+    fn new(values: impl Iterator<Item = f64>, from: f64, to: f64) -> Self {
+        let (mut min, mut max) = values
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        if min > max {
+            min = 0.0;
+            max = 1.0;
+        } else if (max - min).abs() < f64::EPSILON {
+            min -= 1.0;
+            max += 1.0;
+        }
+
+        Self { min, max, from, to }
+    }
+
+    fn map(&self, value: f64) -> f64 {
+        let mapped = self.from + (value - self.min) / (self.max - self.min) * (self.to - self.from);
+
+        (mapped * 10.0).round() / 10.0
+    }
+
+    fn ticks(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..=TICKS).map(move |i| self.min + (self.max - self.min) * i as f64 / TICKS as f64)
+    }
+}
+
+fn format_tick(value: f64) -> String {
+    if value.fract().abs() < 1e-9 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Renders the chosen columns of the given rows as an svg document
+pub fn render_svg(
+    data: &dyn ResultData,
+    rows: &[u64],
+    spec: &ChartSpec,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let x_info = data.column_info(spec.x);
+    let xs = rows
+        .iter()
+        .map(|row| data.value(spec.x, *row))
+        .collect::<Result<Vec<DbType>, _>>()?;
+    let series = spec
+        .ys
+        .iter()
+        .map(|col| {
+            let values = rows
+                .iter()
+                .map(|row| Ok(as_number(&data.value(*col, *row)?)))
+                .collect::<Result<Vec<Option<f64>>, Box<dyn std::error::Error>>>()?;
+
+            Ok((data.column_info(*col).name, values))
+        })
+        .collect::<Result<Vec<(String, Vec<Option<f64>>)>, Box<dyn std::error::Error>>>()?;
+
+    let y = Scale::new(
+        series
+            .iter()
+            .flat_map(|(_, values)| values.iter().flatten().copied())
+            .chain(match spec.kind {
+                ChartKind::Bar => Some(0.0),
+                _ => None,
+            }),
+        HEIGHT - BOTTOM,
+        TOP,
+    );
+
+    // bars are always categorical, other charts use a continuous x axis
+    // when every value has a position on one
+    let positions: Option<Vec<f64>> = match spec.kind {
+        ChartKind::Bar => None,
+        _ => xs.iter().map(position).collect(),
+    };
+    let x = match &positions {
+        Some(positions) => Scale::new(positions.iter().copied(), LEFT, WIDTH - RIGHT),
+        None => Scale::new(
+            vec![-0.5, xs.len() as f64 - 0.5].into_iter(),
+            LEFT,
+            WIDTH - RIGHT,
+        ),
+    };
+    let x_at = |idx: usize| match &positions {
+        Some(positions) => x.map(positions[idx]),
+        None => x.map(idx as f64),
+    };
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-size="11">"#,
+        w = WIDTH,
+        h = HEIGHT
+    );
+
+    // axes
+    svg += &format!(
+        r#"<line x1="{l}" y1="{b}" x2="{r}" y2="{b}" stroke="black"/><line x1="{l}" y1="{t}" x2="{l}" y2="{b}" stroke="black"/>"#,
+        l = LEFT,
+        r = WIDTH - RIGHT,
+        t = TOP,
+        b = HEIGHT - BOTTOM
+    );
+    for tick in y.ticks() {
+        svg += &format!(
+            r#"<text x="{}" y="{}" text-anchor="end" dominant-baseline="middle">{}</text>"#,
+            LEFT - 6.0,
+            y.map(tick),
+            format_tick(tick)
+        );
+    }
+
+    let labelled: Vec<usize> = match &positions {
+        // label the value closest to each tick, so dates read as dates
+        Some(positions) => x
+            .ticks()
+            .filter_map(|tick| {
+                (0..positions.len()).min_by(|a, b| {
+                    (positions[*a] - tick)
+                        .abs()
+                        .partial_cmp(&(positions[*b] - tick).abs())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+            })
+            .collect(),
+        None => {
+            let step = (xs.len() / (TICKS * 2)).max(1);
+            (0..xs.len()).step_by(step).collect()
+        }
+    };
+    for idx in labelled {
+        svg += &format!(
+            r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            x_at(idx),
+            HEIGHT - BOTTOM + 16.0,
+            escape(&xs[idx].to_string())
+        );
+    }
+
+    svg += &format!(
+        r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+        LEFT + (WIDTH - LEFT - RIGHT) / 2.0,
+        HEIGHT - 10.0,
+        escape(&x_info.name)
+    );
+    svg += &format!(
+        r#"<text x="14" y="{y}" text-anchor="middle" transform="rotate(-90 14 {y})">{}</text>"#,
+        escape(
+            &series
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        y = TOP + (HEIGHT - TOP - BOTTOM) / 2.0
+    );
+
+    let band = (WIDTH - LEFT - RIGHT) / xs.len().max(1) as f64;
+    let bar_width = band * 0.8 / series.len().max(1) as f64;
+    for (idx, (name, values)) in series.iter().enumerate() {
+        let colour = COLOURS[idx % COLOURS.len()];
+        let points: Vec<(f64, f64)> = values
+            .iter()
+            .enumerate()
+            .filter_map(|(row, value)| value.map(|value| (row, value)))
+            .map(|(row, value)| (x_at(row), y.map(value)))
+            .collect();
+
+        match spec.kind {
+            ChartKind::Bar => {
+                let zero = y.map(0.0);
+                for (cx, cy) in points {
+                    svg += &format!(
+                        r#"<rect x="{:.1}" y="{}" width="{:.1}" height="{}" fill="{}"><title>{}</title></rect>"#,
+                        cx - band * 0.4 + bar_width * idx as f64,
+                        cy.min(zero),
+                        bar_width,
+                        (zero - cy).abs(),
+                        colour,
+                        escape(name)
+                    );
+                }
+            }
+            ChartKind::Line => {
+                let path = points
+                    .iter()
+                    .map(|(cx, cy)| format!("{},{}", cx, cy))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                svg += &format!(
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"><title>{}</title></polyline>"#,
+                    path,
+                    colour,
+                    escape(name)
+                );
+            }
+            ChartKind::Scatter => {
+                for (cx, cy) in points {
+                    svg += &format!(
+                        r#"<circle cx="{}" cy="{}" r="3" fill="{}"><title>{}</title></circle>"#,
+                        cx,
+                        cy,
+                        colour,
+                        escape(name)
+                    );
+                }
+            }
+        }
+    }
+
+    svg += "</svg>";
+
+    Ok(svg)
+}
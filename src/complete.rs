@@ -0,0 +1,352 @@
+use crate::catalog::Catalog;
+use crate::jse;
+use crate::sql::{is_keyword, quote_identifier, tokenize, Token, TokenKind, KEYWORDS};
+
+/// How many suggestions are offered at once
+const LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompletionKind {
+    Column,
+    Table,
+    Function,
+    Keyword,
+}
+impl CompletionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            CompletionKind::Column => "column",
+            CompletionKind::Table => "table",
+            CompletionKind::Function => "function",
+            CompletionKind::Keyword => "keyword",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+}
+
+/// A table named in a from, join, update or into clause
+#[derive(Debug, Clone, PartialEq)]
+struct TableRef {
+    name: String,
+    alias: Option<String>,
+}
+
+fn unquote(text: &str) -> String {
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        text[1..text.len() - 1].replace("\"\"", "\"")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Quotes identifiers that wouldn't survive being typed as-is
+fn quote(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if plain && !is_keyword(name) {
+        name.to_string()
+    } else {
+        quote_identifier(name)
+    }
+}
+
+fn is_name(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::Identifier | TokenKind::QuotedIdentifier
+    )
+}
+
+fn referenced_tables(query: &str) -> Vec<TableRef> {
+    let tokens: Vec<Token> = tokenize(query)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .collect();
+
+    let mut tables = vec![];
+    let mut clause = String::new();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = tokens[idx];
+        idx += 1;
+
+        let starts_reference = match token.kind {
+            TokenKind::Keyword => {
+                clause = token.text.to_lowercase();
+                matches!(clause.as_str(), "from" | "join" | "update" | "into")
+            }
+            _ => token.text == "," && clause == "from",
+        };
+        if !starts_reference || !tokens.get(idx).map_or(false, is_name) {
+            continue;
+        }
+
+        // only the last part of schema.table is the table's name
+        let mut name = unquote(tokens[idx].text);
+        idx += 1;
+        while tokens.get(idx).map_or(false, |t| t.text == ".")
+            && tokens.get(idx + 1).map_or(false, is_name)
+        {
+            name = unquote(tokens[idx + 1].text);
+            idx += 2;
+        }
+
+        if tokens
+            .get(idx)
+            .map_or(false, |t| t.text.eq_ignore_ascii_case("as"))
+        {
+            idx += 1;
+        }
+        let alias = match tokens.get(idx) {
+            Some(token) if is_name(token) => {
+                idx += 1;
+                Some(unquote(token.text))
+            }
+            _ => None,
+        };
+
+        tables.push(TableRef { name, alias });
+    }
+
+    tables
+}
+
+/// Splits the text before the cursor into what's been typed of the current
+/// word, and the name before a `.` qualifying it
+fn current_word(before: &str) -> (Option<String>, &str) {
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .last()
+        .map_or(before.len(), |(idx, _)| idx);
+    let prefix = &before[start..];
+
+    let qualifier = before[..start]
+        .strip_suffix('.')
+        .and_then(|rest| tokenize(rest).pop())
+        .filter(is_name)
+        .map(|token| unquote(token.text));
+
+    (qualifier, prefix)
+}
+
+/// Suggestions for the word being typed at the cursor, along with that word
+/// so it can be replaced
+pub fn complete<'a>(catalog: &Catalog, before: &'a str, after: &str) -> (&'a str, Vec<Completion>) {
+    let (qualifier, prefix) = current_word(before);
+    let referenced = referenced_tables(&format!("{}{}", before, after));
+    let lowered = prefix.to_lowercase();
+    let matching = |label: &str| label.to_lowercase().starts_with(&lowered);
+
+    let tables = catalog.schemas.iter().flat_map(|schema| &schema.tables);
+    let mut completions: Vec<Completion> = vec![];
+    let mut add = |label: String, kind: CompletionKind| {
+        if matching(&label) && !completions.iter().any(|c| c.label == label) {
+            completions.push(Completion { label, kind });
+        }
+    };
+
+    if let Some(qualifier) = qualifier {
+        let qualifier = qualifier.to_lowercase();
+        let named: Vec<&str> = referenced
+            .iter()
+            .filter(|table| {
+                table.alias.as_deref().unwrap_or(&table.name).to_lowercase() == qualifier
+            })
+            .map(|table| table.name.as_str())
+            .collect();
+
+        for table in tables.clone() {
+            if table.name.to_lowercase() == qualifier || named.contains(&table.name.as_str()) {
+                for column in &table.columns {
+                    add(quote(&column.name), CompletionKind::Column);
+                }
+            }
+        }
+        for schema in &catalog.schemas {
+            if schema.name.to_lowercase() == qualifier {
+                for table in &schema.tables {
+                    add(quote(&table.name), CompletionKind::Table);
+                }
+            }
+        }
+
+        return (prefix, completions);
+    }
+
+    // columns from the tables in the query, or from every table when the
+    // query doesn't name any yet
+    for table in tables.clone() {
+        if referenced.is_empty() || referenced.iter().any(|r| r.name == table.name) {
+            for column in &table.columns {
+                add(quote(&column.name), CompletionKind::Column);
+            }
+        }
+    }
+    for table in tables {
+        add(quote(&table.name), CompletionKind::Table);
+    }
+    for function in &catalog.functions {
+        add(function.clone(), CompletionKind::Function);
+    }
+    for keyword in KEYWORDS {
+        add(keyword.to_string(), CompletionKind::Keyword);
+    }
+
+    // after from, join and friends a table is much more likely than a column
+    let wants_table = tokenize(&before[..before.len() - prefix.len()])
+        .into_iter()
+        .rev()
+        .find(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .map_or(false, |t| {
+            matches!(
+                t.text.to_lowercase().as_str(),
+                "from" | "join" | "update" | "into" | "table" | "describe"
+            )
+        });
+    let rank = |kind: CompletionKind| match kind {
+        CompletionKind::Table if wants_table => 0,
+        CompletionKind::Column if wants_table => 1,
+        kind => kind as u8,
+    };
+    completions.sort_by(|a, b| {
+        rank(a.kind)
+            .cmp(&rank(b.kind))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    completions.truncate(LIMIT);
+
+    (prefix, completions)
+}
+
+/// One line for the replaced word, then a `kind\tlabel` line per completion
+pub fn encode(prefix: &str, completions: &[Completion]) -> String {
+    std::iter::once(prefix.to_string())
+        .chain(
+            completions
+                .iter()
+                .map(|c| format!("{}\t{}", c.kind.name(), c.label)),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Defines `Module.autocomplete`, which the editor's handlers use to show,
+/// navigate and accept the suggestions from the `complete` export
+pub fn install() -> i32 {
+    jse!(
+        b"
+        var state = {list: null, items: [], selected: 0, prefix: ''};
+
+        var close = function () {
+            if (state.list) { state.list.remove(); }
+            state.list = null;
+        };
+
+        var accept = function (editor, idx) {
+            var end = editor.selectionStart;
+            editor.setRangeText(state.items[idx].label, end - state.prefix.length, end, 'end');
+            close();
+            editor.oninput();
+        };
+
+        var render = function (editor) {
+            if (!state.list) {
+                state.list = document.createElement('ul');
+                state.list.className = 'completions';
+                editor.parentNode.appendChild(state.list);
+            }
+            state.list.innerHTML = '';
+            state.items.forEach(function (item, idx) {
+                var li = document.createElement('li');
+                li.textContent = item.label;
+                li.title = item.kind;
+                li.className = 'completion-' + item.kind + (idx === state.selected ? ' selected' : '');
+                li.onmousedown = function (event) {
+                    event.preventDefault();
+                    accept(editor, idx);
+                };
+                state.list.appendChild(li);
+            });
+        };
+
+        var show = function (editor) {
+            var before = editor.value.slice(0, editor.selectionStart);
+            var after = editor.value.slice(editor.selectionEnd);
+            var lines = Module.ccall('complete', 'string', ['string', 'string'], [before, after]).split('\\n');
+
+            state.prefix = lines.shift();
+            state.items = lines.map(function (line) {
+                var tab = line.indexOf('\\t');
+                return {kind: line.slice(0, tab), label: line.slice(tab + 1)};
+            });
+            state.selected = 0;
+
+            if (state.items.length) { render(editor); } else { close(); }
+        };
+
+        Module.autocomplete = {
+            close: close,
+            input: function (editor) {
+                if (state.list || editor.value.charAt(editor.selectionStart - 1) === '.') {
+                    show(editor);
+                }
+            },
+            key: function (editor, key) {
+                if (key === 'Ctrl+ ') {
+                    show(editor);
+                } else if (!state.list) {
+                    return false;
+                } else if (key === 'ArrowDown' || key === 'ArrowUp') {
+                    var step = key === 'ArrowDown' ? 1 : state.items.length - 1;
+                    state.selected = (state.selected + step) % state.items.length;
+                    render(editor);
+                } else if (key === 'Enter' || key === 'Tab') {
+                    accept(editor, state.selected);
+                } else if (key === 'Escape') {
+                    close();
+                } else {
+                    return false;
+                }
+                return true;
+            }
+        };
+    \x00"
+    )
+}
+
+/// Refreshes the open completions after the editor's text changed, or
+/// opens them after a `.`
+pub fn input() -> i32 {
+    jse!(
+        b"
+        var editor = document.querySelector && document.querySelector('textarea[name=query]');
+        if (editor) { Module.autocomplete.input(editor); }
+    \x00"
+    )
+}
+
+/// Passes a key pressed in the editor, as in an `Event`'s payload, to the
+/// completions, returning whether they used it
+pub fn key(key: &str) -> bool {
+    jse!(
+        -> bool,
+        b"
+        var editor = document.querySelector && document.querySelector('textarea[name=query]');
+        return editor && Module.autocomplete.key(editor, UTF8ToString($0, $1)) ? 1 : 0;
+    \x00",
+        key
+    )
+}
@@ -0,0 +1,425 @@
+use crate::error::{ErrorKind, Position, QueryError};
+use crate::types::{
+    duckdb_blob, duckdb_date, duckdb_hugeint, duckdb_interval, duckdb_time, duckdb_timestamp,
+    duckdb_type as DuckDBType, ALL_TYPES,
+};
+use crate::{ColumnInfo, DbType, ResolvedResult};
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Rows and columns that can be rendered, whether they're still held by
+/// DuckDB or were sent over from the worker
+pub trait ResultData: Debug {
+    fn column_count(&self) -> u64;
+    fn column_info(&self, col: u64) -> ColumnInfo;
+    fn row_count(&self) -> u64;
+    fn value(&self, col: u64, row: u64) -> Result<DbType, Box<dyn Error>>;
+    /// Whether the value is NULL, in which case `value` is a placeholder
+    fn is_null(&self, col: u64, row: u64) -> bool;
+    fn execution_time(&self) -> Duration;
+    fn rows_affected(&self) -> Option<i64>;
+}
+impl<'a> ResultData for ResolvedResult<'a> {
+    fn column_count(&self) -> u64 {
+        ResolvedResult::column_count(self)
+    }
+
+    fn column_info(&self, col: u64) -> ColumnInfo {
+        ResolvedResult::column_info(self, col)
+    }
+
+    fn row_count(&self) -> u64 {
+        ResolvedResult::row_count(self)
+    }
+
+    fn value(&self, col: u64, row: u64) -> Result<DbType, Box<dyn Error>> {
+        self.consume(col, row)
+    }
+
+    fn is_null(&self, col: u64, row: u64) -> bool {
+        ResolvedResult::is_null(self, col, row)
+    }
+
+    fn execution_time(&self) -> Duration {
+        ResolvedResult::execution_time(self)
+    }
+
+    fn rows_affected(&self) -> Option<i64> {
+        ResolvedResult::rows_affected(self)
+    }
+}
+
+/// A result copied out of DuckDB, one `Vec` of values per row
+#[derive(Debug, Clone)]
+pub struct OwnedResult {
+    pub columns: Vec<ColumnInfo>,
+    pub rows: Vec<Vec<DbType>>,
+    /// Whether each value in `rows` is NULL
+    pub nulls: Vec<Vec<bool>>,
+    pub execution: Duration,
+    pub rows_affected: Option<i64>,
+}
+impl OwnedResult {
+    pub fn copy(data: &dyn ResultData) -> Result<Self, Box<dyn Error>> {
+        let columns = (0..data.column_count())
+            .map(|col| data.column_info(col))
+            .collect();
+        let rows = (0..data.row_count())
+            .map(|row| {
+                (0..data.column_count())
+                    .map(|col| data.value(col, row))
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<DbType>>, _>>()?;
+        let nulls = (0..data.row_count())
+            .map(|row| {
+                (0..data.column_count())
+                    .map(|col| data.is_null(col, row))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            columns,
+            rows,
+            nulls,
+            execution: data.execution_time(),
+            rows_affected: data.rows_affected(),
+        })
+    }
+}
+impl ResultData for OwnedResult {
+    fn column_count(&self) -> u64 {
+        self.columns.len() as u64
+    }
+
+    fn column_info(&self, col: u64) -> ColumnInfo {
+        self.columns[col as usize].clone()
+    }
+
+    fn row_count(&self) -> u64 {
+        self.rows.len() as u64
+    }
+
+    fn value(&self, col: u64, row: u64) -> Result<DbType, Box<dyn Error>> {
+        self.rows
+            .get(row as usize)
+            .and_then(|values| values.get(col as usize))
+            .cloned()
+            .ok_or_else(|| format!("no value at column {}, row {}", col, row).into())
+    }
+
+    fn is_null(&self, col: u64, row: u64) -> bool {
+        self.nulls
+            .get(row as usize)
+            .and_then(|nulls| nulls.get(col as usize))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    fn execution_time(&self) -> Duration {
+        self.execution
+    }
+
+    fn rows_affected(&self) -> Option<i64> {
+        self.rows_affected
+    }
+}
+
+#[derive(Debug, Default)]
+struct Writer(Vec<u8>);
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    fn value(&mut self, value: &DbType) {
+        use crate::DbType::*;
+
+        let tag: &'static str = value.into();
+        self.str(tag);
+        match value {
+            Boolean(v) => self.u8(*v as u8),
+            Tinyint(v) => self.u8(*v as u8),
+            Smallint(v) => self.u32(*v as u32),
+            Integer(v) => self.u32(*v as u32),
+            Bigint(v) => self.u64(*v as u64),
+            Float(v) => self.u32(v.to_bits()),
+            Double(v) => self.u64(v.to_bits()),
+            Date(v) => self.date(v),
+            Time(v) => self.time(v),
+            Timestamp(v) => {
+                self.date(&v.date);
+                self.time(&v.time);
+            }
+            String(v) => self.str(v),
+            Interval(v) => {
+                self.u32(v.months as u32);
+                self.u32(v.days as u32);
+                self.u64(v.micros as u64);
+            }
+            Hugeint(v) => {
+                self.u64(v.lower);
+                self.u64(v.upper as u64);
+            }
+            Blob(v) => self.bytes(v.bytes()),
+            Unknown(type_) => self.u32(*type_ as u32),
+        }
+    }
+
+    fn date(&mut self, date: &duckdb_date) {
+        self.u32(date.year as u32);
+        self.u8(date.month as u8);
+        self.u8(date.day as u8);
+    }
+
+    fn time(&mut self, time: &duckdb_time) {
+        self.u8(time.hour as u8);
+        self.u8(time.min as u8);
+        self.u8(time.sec as u8);
+        self.u32(time.micros as u32);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        if self.0.len() < len {
+            return Err("truncated result".into());
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], Box<dyn Error>> {
+        let len = self.u32()? as usize;
+
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(std::str::from_utf8(self.bytes()?)?.to_string())
+    }
+
+    fn type_(&mut self) -> Result<DuckDBType, Box<dyn Error>> {
+        let discriminant = self.u32()?;
+
+        Ok(ALL_TYPES
+            .iter()
+            .copied()
+            .find(|type_| *type_ as u32 == discriminant)
+            .unwrap_or(DuckDBType::DUCKDB_TYPE_INVALID))
+    }
+
+    fn value(&mut self) -> Result<DbType, Box<dyn Error>> {
+        use crate::DbType::*;
+
+        Ok(match self.string()?.as_str() {
+            "Boolean" => Boolean(self.u8()? != 0),
+            "Tinyint" => Tinyint(self.u8()? as i8),
+            "Smallint" => Smallint(self.u32()? as i16),
+            "Integer" => Integer(self.u32()? as i32),
+            "Bigint" => Bigint(self.u64()? as i64),
+            "Float" => Float(f32::from_bits(self.u32()?)),
+            "Double" => Double(f64::from_bits(self.u64()?)),
+            "Date" => Date(self.date()?),
+            "Time" => Time(self.time()?),
+            "Timestamp" => Timestamp(duckdb_timestamp::new(self.date()?, self.time()?)),
+            "String" => String(self.string()?),
+            "Interval" => Interval(duckdb_interval {
+                months: self.u32()? as i32,
+                days: self.u32()? as i32,
+                micros: self.u64()? as i64,
+            }),
+            "Hugeint" => Hugeint(duckdb_hugeint {
+                lower: self.u64()?,
+                upper: self.u64()? as i64,
+            }),
+            "Blob" => Blob(duckdb_blob::new(self.bytes()?)),
+            "Unknown" => Unknown(self.type_()?),
+            other => return Err(format!("unknown value type {:?}", other).into()),
+        })
+    }
+
+    fn date(&mut self) -> Result<duckdb_date, Box<dyn Error>> {
+        Ok(duckdb_date::new(
+            self.u32()? as i32,
+            self.u8()? as i8,
+            self.u8()? as i8,
+        ))
+    }
+
+    fn time(&mut self) -> Result<duckdb_time, Box<dyn Error>> {
+        Ok(duckdb_time::new(
+            self.u8()? as i8,
+            self.u8()? as i8,
+            self.u8()? as i8,
+            self.u32()? as i16,
+        ))
+    }
+}
+
+/// Serializes the outcome of a query so it can be posted to another
+/// context, see `decode`
+pub fn encode(reply: Result<&dyn ResultData, &QueryError>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut writer = Writer::default();
+
+    match reply {
+        Ok(data) => {
+            writer.u8(0);
+            writer.u32(data.column_count() as u32);
+            for col in 0..data.column_count() {
+                let info = data.column_info(col);
+                writer.str(&info.name);
+                writer.u32(info.type_ as u32);
+                writer.str(&info.sql_type);
+            }
+            writer.u64(data.row_count());
+            // a bitmap per column, with bit `row % 8` of byte `row / 8` set
+            // where the value is NULL
+            for col in 0..data.column_count() {
+                let mut nulls = vec![0u8; (data.row_count() as usize + 7) / 8];
+                for row in 0..data.row_count() {
+                    if data.is_null(col, row) {
+                        nulls[row as usize / 8] |= 1 << (row % 8);
+                    }
+                }
+                writer.bytes(&nulls);
+            }
+            for row in 0..data.row_count() {
+                for col in 0..data.column_count() {
+                    writer.value(&data.value(col, row)?);
+                }
+            }
+            writer.u64(data.execution_time().as_micros() as u64);
+            match data.rows_affected() {
+                Some(count) => {
+                    writer.u8(1);
+                    writer.u64(count as u64);
+                }
+                None => writer.u8(0),
+            }
+        }
+        Err(error) => {
+            writer.u8(1);
+            writer.str(&error.query);
+            writer.str(error.kind.name());
+            writer.str(&error.message);
+            match error.position {
+                Some(position) => {
+                    writer.u8(1);
+                    writer.u32(position.line as u32);
+                    writer.u32(position.column as u32);
+                }
+                None => writer.u8(0),
+            }
+        }
+    }
+
+    Ok(writer.0)
+}
+
+/// Reads back what `encode` wrote, failing only if it's malformed
+pub fn decode(bytes: &[u8]) -> Result<Result<OwnedResult, QueryError>, Box<dyn Error>> {
+    let mut reader = Reader(bytes);
+
+    Ok(match reader.u8()? {
+        0 => {
+            let columns = (0..reader.u32()?)
+                .map(|_| {
+                    Ok(ColumnInfo {
+                        name: reader.string()?,
+                        type_: reader.type_()?,
+                        sql_type: reader.string()?,
+                    })
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+            let row_count = reader.u64()?;
+            let bitmaps = columns
+                .iter()
+                .map(|_| reader.bytes())
+                .collect::<Result<Vec<_>, _>>()?;
+            let rows = (0..row_count)
+                .map(|_| columns.iter().map(|_| reader.value()).collect())
+                .collect::<Result<Vec<Vec<DbType>>, _>>()?;
+            let execution = Duration::from_micros(reader.u64()?);
+            let rows_affected = match reader.u8()? {
+                0 => None,
+                _ => Some(reader.u64()? as i64),
+            };
+
+            let nulls = (0..row_count)
+                .map(|row| {
+                    bitmaps
+                        .iter()
+                        .map(|nulls| {
+                            nulls
+                                .get(row as usize / 8)
+                                .map_or(false, |byte| byte & (1 << (row % 8)) != 0)
+                        })
+                        .collect()
+                })
+                .collect();
+
+            Ok(OwnedResult {
+                columns,
+                rows,
+                nulls,
+                execution,
+                rows_affected,
+            })
+        }
+        _ => {
+            let query = reader.string()?;
+            let kind = ErrorKind::from_name(&reader.string()?);
+            let message = reader.string()?;
+            let position = match reader.u8()? {
+                0 => None,
+                _ => Some(Position {
+                    line: reader.u32()? as usize,
+                    column: reader.u32()? as usize,
+                }),
+            };
+
+            Err(QueryError {
+                query,
+                kind,
+                message,
+                position,
+            })
+        }
+    })
+}
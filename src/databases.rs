@@ -0,0 +1,145 @@
+use crate::catalog;
+use crate::db::{Connection, DB};
+use std::cell::RefCell;
+use std::thread_local;
+
+/// An open database with the one connection its queries run on, kept for
+/// as long as it's open so `SET` variables and temporary tables last from
+/// one query to the next
+#[derive(Debug)]
+struct Database {
+    name: String,
+    /// `None` for in-memory databases
+    path: Option<String>,
+    connection: Connection,
+}
+
+thread_local! {
+    static DATABASES: RefCell<Vec<Database>> = RefCell::new(Vec::new());
+    static CURRENT: RefCell<Option<String>> = RefCell::new(None);
+}
+
+pub fn is_open(name: &str) -> bool {
+    DATABASES.with(|databases| databases.borrow().iter().any(|db| db.name == name))
+}
+
+/// In-memory databases are told apart by number
+fn memory_name() -> String {
+    (1..)
+        .map(|n| match n {
+            1 => "memory".to_string(),
+            n => format!("memory {}", n),
+        })
+        .find(|name| !is_open(name))
+        .expect("names run out")
+}
+
+/// Opens the database at `path`, or a new in-memory one, and makes it the
+/// current one. A file that's already open is only selected, keeping its
+/// connection and whatever the session set up
+pub fn open(path: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    let name = match path {
+        Some(path) if select(path) => return Ok(path.to_string()),
+        Some(path) => path.to_string(),
+        None => memory_name(),
+    };
+
+    let connection = DB::new(path)?.connection()?;
+    DATABASES.with(|databases| {
+        databases.borrow_mut().push(Database {
+            name: name.clone(),
+            path: path.map(str::to_string),
+            connection,
+        })
+    });
+    select(&name);
+
+    Ok(name)
+}
+
+/// Makes `name` the database queries run against, if it's open
+pub fn select(name: &str) -> bool {
+    if !is_open(name) {
+        return false;
+    }
+
+    CURRENT.with(|current| current.replace(Some(name.to_string())));
+    catalog::invalidate();
+
+    true
+}
+
+/// Closes `name`, moving on to the first still open if it was current
+pub fn close(name: &str) {
+    DATABASES.with(|databases| databases.borrow_mut().retain(|db| db.name != name));
+
+    if current().as_deref() == Some(name) {
+        CURRENT.with(|current| current.replace(None));
+        if let Some(first) = names().first() {
+            select(first);
+        }
+    }
+}
+
+/// The open databases, in the order they were opened
+pub fn names() -> Vec<String> {
+    DATABASES.with(|databases| {
+        databases
+            .borrow()
+            .iter()
+            .map(|db| db.name.clone())
+            .collect()
+    })
+}
+
+pub fn current() -> Option<String> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Where the current database is stored, `None` if it's in memory
+pub fn current_path() -> Option<String> {
+    let name = current()?;
+
+    DATABASES.with(|databases| {
+        databases
+            .borrow()
+            .iter()
+            .find(|db| db.name == name)?
+            .path
+            .clone()
+    })
+}
+
+/// Runs `f` with the current database's connection, `None` if nothing's open
+pub fn with_connection<T>(f: impl FnOnce(&Connection) -> T) -> Option<T> {
+    let name = current()?;
+
+    DATABASES.with(|databases| {
+        let databases = databases.borrow();
+        let db = databases.iter().find(|db| db.name == name)?;
+
+        Some(f(&db.connection))
+    })
+}
+
+/// The name and file of each database attached to `conn`, including its
+/// own, or nothing if DuckDB can't list them
+pub fn attached(conn: &Connection) -> Vec<(String, String)> {
+    let list = match conn.query("pragma database_list") {
+        Ok(list) if list.column_count() >= 3 => list,
+        _ => return vec![],
+    };
+
+    (0..list.row_count())
+        .filter_map(|row| {
+            let name = list.consume(1, row).ok()?.to_string();
+            let file = if list.is_null(2, row) {
+                String::new()
+            } else {
+                list.consume(2, row).ok()?.to_string()
+            };
+
+            Some((name, file))
+        })
+        .collect()
+}
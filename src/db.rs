@@ -1,21 +1,53 @@
-use crate::types::{duckdb_connection, duckdb_database};
+use crate::error::{ErrorKind, QueryError};
+use crate::sql::{is_dml, statement_keywords};
+use crate::types::{duckdb_connection, duckdb_database, DuckDBResult};
 use crate::{
-    duckdb_disconnect, duckdb_open, duckdb_query, ext_duckdb_close, malloc, DuckDBState,
-    ResolvedResult, PTR,
+    c_char, duckdb_destroy_result, duckdb_disconnect, duckdb_open, ext_duckdb_close, malloc,
+    DbType, DuckDBState, ResolvedResult,
 };
+use std::cell::Cell;
 use std::ffi::{CStr, CString};
+#[cfg(not(target_os = "emscripten"))]
+use std::sync::Condvar;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 extern "C" {
-    fn create_connection(db: *const duckdb_database) -> *const duckdb_connection;
+    fn create_connection(db: duckdb_database) -> *const duckdb_connection;
+
+    fn ext_duckdb_query(
+        con: *const duckdb_connection,
+        query: *const c_char,
+        result: *const DuckDBResult,
+        types: *mut *mut c_char,
+    ) -> DuckDBState;
+
+    fn ext_duckdb_interrupt(con: *const duckdb_connection);
 }
 
 #[derive(Debug)]
+struct RawDatabase(*const duckdb_database);
+// DuckDB synchronises access to a database between its connections
+unsafe impl Send for RawDatabase {}
+unsafe impl Sync for RawDatabase {}
+impl Drop for RawDatabase {
+    fn drop(&mut self) {
+        unsafe {
+            ext_duckdb_close(self.0);
+            libc::free(self.0 as *mut libc::c_void);
+        }
+    }
+}
+
+/// An open database. Clones share it, and it's closed once they and every
+/// `Connection` to it are dropped
+#[derive(Debug, Clone)]
 pub struct DB {
-    db: *const duckdb_database,
+    db: Arc<RawDatabase>,
 }
 impl DB {
     pub fn new(path: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
-        let db = malloc(PTR);
+        let db = malloc();
 
         unsafe {
             match path {
@@ -27,47 +59,289 @@ impl DB {
             }
         };
 
-        Ok(Self { db })
+        Ok(Self {
+            db: Arc::new(RawDatabase(db)),
+        })
     }
 
     pub fn connection(&self) -> Result<Connection, Box<dyn std::error::Error>> {
-        let connection: *const duckdb_connection = unsafe { create_connection(self.db) };
-        println!("conn: {:?}", &connection);
-        Ok(Connection { connection })
+        // duckdb_open filled in the handle `db` points to
+        let connection: *const duckdb_connection = unsafe { create_connection(*self.db.0) };
+        Ok(Connection {
+            connection,
+            interrupt: InterruptHandle::new(connection),
+            default_timeout: None,
+            settings_changed: Cell::new(false),
+            _db: self.clone(),
+        })
     }
 }
-impl Drop for DB {
-    fn drop(&mut self) {
-        println!("Dropping {:?}", self);
-        unsafe { ext_duckdb_close(self.db) };
+
+#[derive(Debug)]
+struct RawConnection(*const duckdb_connection);
+// interrupting only sets an atomic flag on the connection
+unsafe impl Send for RawConnection {}
+
+/// Stops whatever query is running on a `Connection`, from somewhere that
+/// can't borrow it, like a timer or another thread. Does nothing once the
+/// connection is closed
+#[derive(Debug, Clone)]
+pub struct InterruptHandle {
+    connection: Arc<Mutex<Option<RawConnection>>>,
+}
+impl InterruptHandle {
+    fn new(connection: *const duckdb_connection) -> Self {
+        Self {
+            connection: Arc::new(Mutex::new(Some(RawConnection(connection)))),
+        }
+    }
+
+    /// Stops this and its clones from reaching the connection
+    fn detach(&self) {
+        self.connection.lock().expect("poisoned").take();
+    }
+
+    /// The query fails with an `ErrorKind::Interrupted` error
+    pub fn interrupt(&self) {
+        if let Some(connection) = &*self.connection.lock().expect("poisoned") {
+            unsafe { ext_duckdb_interrupt(connection.0) };
+        }
     }
 }
 
+#[cfg(target_os = "emscripten")]
+const NO_THREADS: &str = "Timeouts need threads, which this build doesn't have";
+
+/// Interrupts a query that's still running at its deadline
+#[cfg(not(target_os = "emscripten"))]
+struct Watchdog {
+    /// Whether the query has finished, locked while interrupting so it
+    /// can't be interrupted once `stop` has returned
+    finished: Arc<(Mutex<bool>, Condvar)>,
+    thread: std::thread::JoinHandle<bool>,
+}
+#[cfg(not(target_os = "emscripten"))]
+impl Watchdog {
+    fn start(handle: InterruptHandle, timeout: Duration) -> Self {
+        // a condvar needs a mutex, not an atomic
+        #[allow(clippy::mutex_atomic)]
+        let finished = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread = {
+            let finished = finished.clone();
+            std::thread::spawn(move || {
+                let (lock, changed) = &*finished;
+                let (mut done, _) = changed
+                    .wait_timeout_while(lock.lock().expect("poisoned"), timeout, |done| !*done)
+                    .expect("poisoned");
+                if *done {
+                    return false;
+                }
+
+                // DuckDB clears the flag when a query starts, so one that
+                // hadn't quite started yet needs telling again
+                while !*done {
+                    handle.interrupt();
+                    done = changed
+                        .wait_timeout(done, Duration::from_millis(10))
+                        .expect("poisoned")
+                        .0;
+                }
+
+                true
+            })
+        };
+
+        Self { finished, thread }
+    }
+
+    /// Whether the deadline passed before the query finished
+    fn stop(self) -> bool {
+        let (lock, changed) = &*self.finished;
+        *lock.lock().expect("poisoned") = true;
+        changed.notify_one();
+
+        self.thread.join().unwrap_or(false)
+    }
+}
+
+/// Lists the session's temporary tables and views
+const TEMPORARY_QUERY: &str =
+    "select table_name from information_schema.tables where table_schema = 'temp'";
+
+/// A session on a database, which it keeps open. It can be handed to
+/// another thread, but DuckDB doesn't expect two to use one at once, so
+/// it isn't `Sync`
 #[derive(Debug)]
 pub struct Connection {
     connection: *const duckdb_connection,
+    interrupt: InterruptHandle,
+    default_timeout: Option<Duration>,
+    /// Whether a statement like `SET` changed the session
+    settings_changed: Cell<bool>,
+    // released after disconnecting, in case it's the last
+    _db: DB,
 }
+unsafe impl Send for Connection {}
 impl Connection {
-    pub fn query(&self, que: &str) -> Result<ResolvedResult, Box<dyn std::error::Error>> {
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    pub fn default_timeout(&self) -> Option<Duration> {
+        self.default_timeout
+    }
+
+    /// Applied to every `query` from now on, `None` to wait indefinitely
+    #[cfg(not(target_os = "emscripten"))]
+    pub fn set_default_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.default_timeout = timeout;
+
+        Ok(())
+    }
+
+    /// Emscripten builds can't time queries out, see `query_with_timeout`,
+    /// so only `None` is accepted
+    #[cfg(target_os = "emscripten")]
+    pub fn set_default_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match timeout {
+            Some(_) => Err(NO_THREADS.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// The result owns its memory, so it can outlive this connection
+    pub fn query(&self, que: &str) -> Result<ResolvedResult<'static>, QueryError> {
+        match self.default_timeout {
+            Some(timeout) => self.query_with_timeout(que, timeout),
+            None => self.run(que),
+        }
+    }
+
+    /// Interrupts `que` if it's still running after `timeout`, failing
+    /// with an `ErrorKind::Timeout` error
+    #[cfg(not(target_os = "emscripten"))]
+    pub fn query_with_timeout(
+        &self,
+        que: &str,
+        timeout: Duration,
+    ) -> Result<ResolvedResult<'static>, QueryError> {
+        let watchdog = Watchdog::start(self.interrupt_handle(), timeout);
+        let result = self.run(que);
+        let expired = watchdog.stop();
+
+        match result {
+            Err(QueryError {
+                kind: ErrorKind::Interrupted,
+                ..
+            }) if expired => Err(QueryError::timed_out(que, timeout)),
+            result => result,
+        }
+    }
+
+    /// Emscripten builds have no threads, so nothing could interrupt `que`
+    /// while it runs. Rather than wait past the deadline, this fails
+    /// without running it
+    #[cfg(target_os = "emscripten")]
+    pub fn query_with_timeout(
+        &self,
+        que: &str,
+        _timeout: Duration,
+    ) -> Result<ResolvedResult<'static>, QueryError> {
+        Err(QueryError::unsupported(que, NO_THREADS))
+    }
+
+    /// Puts the session back the way `DB::connection` left it, as far as
+    /// that's possible, failing if it changed in a way that can't be undone
+    pub(crate) fn reset(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        // handles given out so far mustn't interrupt whoever's next
+        self.interrupt.detach();
+        self.interrupt = InterruptHandle::new(self.connection);
+        self.default_timeout = None;
+        // fails when there's no transaction to roll back
+        self.run("rollback").ok();
+
+        if self.settings_changed.get() {
+            return Err("The session's settings were changed".into());
+        }
+        let temporary = self.run(TEMPORARY_QUERY)?;
+        if temporary.row_count() > 0 {
+            return Err("The session has temporary tables".into());
+        }
+
+        Ok(())
+    }
+
+    fn run(&self, que: &str) -> Result<ResolvedResult<'static>, QueryError> {
+        if statement_keywords(que).iter().any(|keyword| {
+            matches!(
+                keyword.as_str(),
+                "set" | "reset" | "pragma" | "attach" | "detach"
+            )
+        }) {
+            self.settings_changed.set(true);
+        }
+
         unsafe {
             let s = CString::new(que).expect("string");
 
-            let result = malloc(PTR);
-            let status = duckdb_query(self.connection, s.as_ptr(), result);
+            let result = malloc();
+            let mut types = std::ptr::null_mut();
+            let start = Instant::now();
+            let status = ext_duckdb_query(self.connection, s.as_ptr(), result, &mut types);
+            let logical_types = take_types(types);
 
             if matches!(status, DuckDBState::DuckDBError) {
                 let error_message = CStr::from_ptr((*result).error_message).to_string_lossy();
+                let error = QueryError::parse(que, &error_message);
+                // a failed result still owns its message
+                duckdb_destroy_result(result);
+                libc::free(result as *mut libc::c_void);
 
-                Err(string_error::new_err(&*error_message))
+                Err(error)
             } else {
-                Ok(ResolvedResult::new(result))
+                let mut resolved = ResolvedResult::new(result);
+                resolved.execution = start.elapsed();
+                resolved.logical_types = logical_types;
+
+                if is_dml(que) && resolved.row_count() == 1 {
+                    if let Ok(DbType::Bigint(count)) = resolved.consume(0, 0) {
+                        resolved.rows_affected = Some(count);
+                    }
+                }
+
+                Ok(resolved)
             }
         }
     }
 }
+
 impl Drop for Connection {
     fn drop(&mut self) {
-        println!("Dropping {:?}", self);
-        unsafe { duckdb_disconnect(self.connection) };
+        self.interrupt.detach();
+        // takes the handle's address, to null it out
+        unsafe { duckdb_disconnect(&self.connection as *const _ as *const duckdb_connection) };
+    }
+}
+
+/// Reads, then frees, the newline separated type names `ext_duckdb_query`
+/// hands back
+unsafe fn take_types(types: *mut c_char) -> Vec<String> {
+    if types.is_null() {
+        return vec![];
     }
+
+    let names = CStr::from_ptr(types)
+        .to_string_lossy()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    libc::free(types as *mut libc::c_void);
+
+    names
 }
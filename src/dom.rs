@@ -0,0 +1,127 @@
+use crate::{events, jse};
+
+/// A named container in the page, rendered independently of the others
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Sidebar,
+    Editor,
+    Files,
+    History,
+    Status,
+    Results,
+}
+impl Region {
+    pub const ALL: [Region; 6] = [
+        Region::Sidebar,
+        Region::Editor,
+        Region::Files,
+        Region::History,
+        Region::Status,
+        Region::Results,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Region::Sidebar => "sidebar",
+            Region::Editor => "editor",
+            Region::Files => "files",
+            Region::History => "history",
+            Region::Status => "status",
+            Region::Results => "results",
+        }
+    }
+}
+
+/// Replaces the page with an empty container for each region
+pub fn mount() -> i32 {
+    let regions: String = Region::ALL
+        .iter()
+        .map(|region| format!(r#"<div id="{}"></div>"#, region.id()))
+        .collect();
+
+    crate::set_body_html(format!("<div>{}</div>", regions))
+}
+
+/// Re-renders one region, leaving the rest of the page alone. Event
+/// handlers registered since the last patch go with `html`, so render it
+/// just before
+pub fn patch(region: Region, html: String) -> i32 {
+    events::claim(region);
+
+    jse!(
+        b"
+        var region = document.getElementById && document.getElementById(UTF8ToString($0, $1));
+        if (region) { region.innerHTML = UTF8ToString($2, $3); }
+    \x00",
+        region.id(),
+        &html
+    )
+}
+
+/// Shows `query` in the editor without re-rendering it, so focus, undo and
+/// the cursor survive when it's already there
+pub fn set_editor_query(query: &str) -> i32 {
+    jse!(
+        b"
+        var editor = document.querySelector && document.querySelector('textarea[name=query]');
+        var query = UTF8ToString($0, $1);
+        if (editor && editor.value !== query) {
+            editor.value = query;
+            editor.oninput();
+        }
+    \x00",
+        query
+    )
+}
+
+/// Redraws the highlighted copy of the query behind the editor
+pub fn set_editor_highlight(html: &str) -> i32 {
+    jse!(
+        b"
+        var overlay = document.querySelector && document.querySelector('.editor pre code');
+        if (overlay) { overlay.innerHTML = UTF8ToString($0, $1); }
+    \x00",
+        html
+    )
+}
+
+/// Replaces the editor's selection with `text`, as typing it would
+pub fn insert_in_editor(text: &str) -> i32 {
+    jse!(
+        b"
+        var editor = document.querySelector && document.querySelector('textarea[name=query]');
+        if (editor) {
+            editor.setRangeText(UTF8ToString($0, $1), editor.selectionStart, editor.selectionEnd, 'end');
+            editor.oninput();
+        }
+    \x00",
+        text
+    )
+}
+
+/// Submits the editor's form, running the query in it
+pub fn submit_editor() -> i32 {
+    jse!(
+        b"
+        var editor = document.querySelector && document.querySelector('textarea[name=query]');
+        if (editor) { editor.form.requestSubmit(); }
+    \x00"
+    )
+}
+
+/// Asks for a line of text, `None` if cancelled
+pub fn prompt(message: &str, default: &str) -> Option<String> {
+    jse!(
+        -> Option<String>,
+        b"
+        var answer = prompt(UTF8ToString($0, $1), UTF8ToString($2, $3));
+        return answer === null ? 0 : allocateUTF8(answer);
+    \x00",
+        message,
+        default
+    )
+}
+
+pub fn confirm(message: &str) -> bool {
+    jse!(-> bool, b"return confirm(UTF8ToString($0, $1));\x00", message)
+}
@@ -0,0 +1,194 @@
+use crate::sql::{tokenize, TokenKind};
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+/// The category DuckDB prefixes its messages with, like `Parser Error: `
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    Parser,
+    Binder,
+    Catalog,
+    Conversion,
+    Constraint,
+    /// Stopped by an `InterruptHandle`, or cancelled in the worker
+    Interrupted,
+    /// Ran past the deadline given to `Connection::query_with_timeout`
+    Timeout,
+    /// Asked for something this build can't do
+    Unsupported,
+    Other(String),
+    /// The message didn't say
+    Unknown,
+}
+impl ErrorKind {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Unknown" => ErrorKind::Unknown,
+            "Parser" | "Syntax" => ErrorKind::Parser,
+            "Binder" => ErrorKind::Binder,
+            "Catalog" => ErrorKind::Catalog,
+            "Conversion" => ErrorKind::Conversion,
+            "Constraint" => ErrorKind::Constraint,
+            "INTERRUPT" | "Interrupt" => ErrorKind::Interrupted,
+            "Timeout" => ErrorKind::Timeout,
+            "Unsupported" => ErrorKind::Unsupported,
+            other => ErrorKind::Other(other.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ErrorKind::Parser => "Parser",
+            ErrorKind::Binder => "Binder",
+            ErrorKind::Catalog => "Catalog",
+            ErrorKind::Conversion => "Conversion",
+            ErrorKind::Constraint => "Constraint",
+            ErrorKind::Interrupted => "Interrupt",
+            ErrorKind::Timeout => "Timeout",
+            ErrorKind::Unsupported => "Unsupported",
+            ErrorKind::Other(name) => name,
+            ErrorKind::Unknown => "Unknown",
+        }
+    }
+}
+
+/// Where in the query an error was reported, `line` counting from 1 and
+/// `column` a byte offset into that line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A failed query, with DuckDB's message pulled apart
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub query: String,
+    pub kind: ErrorKind,
+    pub message: String,
+    pub position: Option<Position>,
+}
+impl QueryError {
+    pub fn parse(query: &str, raw: &str) -> Self {
+        let (first, context) = match raw.find("\nLINE ") {
+            Some(idx) => (&raw[..idx], Some(&raw[idx + 1..])),
+            None => (raw, None),
+        };
+        let (kind, message) = match first.find(" Error: ") {
+            Some(idx) => (
+                ErrorKind::from_name(&first[..idx]),
+                &first[idx + " Error: ".len()..],
+            ),
+            None => (ErrorKind::Unknown, first),
+        };
+
+        Self {
+            query: query.to_string(),
+            kind,
+            message: message.trim().to_string(),
+            position: context.and_then(|context| position(query, context)),
+        }
+    }
+
+    /// An error that didn't come from DuckDB, so has no message to parse
+    pub fn new(query: &str, kind: ErrorKind, message: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            kind,
+            message: message.to_string(),
+            position: None,
+        }
+    }
+
+    pub fn timed_out(query: &str, timeout: Duration) -> Self {
+        Self::new(
+            query,
+            ErrorKind::Timeout,
+            &format!("Query took longer than {:?}", timeout),
+        )
+    }
+
+    pub fn unsupported(query: &str, message: &str) -> Self {
+        Self::new(query, ErrorKind::Unsupported, message)
+    }
+
+    /// Byte offset of the position into the whole query
+    pub fn offset(&self) -> Option<usize> {
+        let position = self.position?;
+        let line_start: usize = self
+            .query
+            .split('\n')
+            .take(position.line - 1)
+            .map(|line| line.len() + 1)
+            .sum();
+
+        Some((line_start + position.column).min(self.query.len()))
+    }
+
+    /// The token the error points at, empty when it points past the end
+    pub fn span(&self) -> Option<Range<usize>> {
+        let offset = self.offset()?;
+
+        let mut start = 0;
+        for token in tokenize(&self.query) {
+            let end = start + token.text.len();
+            if offset < end {
+                return Some(if token.kind == TokenKind::Whitespace {
+                    start..start
+                } else {
+                    start..end
+                });
+            }
+            start = end;
+        }
+
+        Some(offset..offset)
+    }
+}
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Unknown => write!(f, "{}", self.message)?,
+            ref kind => write!(f, "{} Error: {}", kind.name(), self.message)?,
+        }
+        match self.position {
+            Some(position) => write!(
+                f,
+                " (line {}, column {})",
+                position.line,
+                position.column + 1
+            ),
+            None => Ok(()),
+        }
+    }
+}
+impl std::error::Error for QueryError {}
+
+/// Finds the caret from a `LINE n: ...` context block in the query.
+/// DuckDB cuts long lines down to a window around the error, marking the
+/// cuts with `...`, so the window is located in the query's own line
+fn position(query: &str, context: &str) -> Option<Position> {
+    let mut lines = context.lines();
+    let first = lines.next()?.strip_prefix("LINE ")?;
+    let colon = first.find(": ")?;
+    let line: usize = first[..colon].parse().ok()?;
+    let snippet = &first[colon + 2..];
+    let caret = lines
+        .next()?
+        .find('^')?
+        .checked_sub("LINE ".len() + colon + 2)?;
+
+    let query_line = query.split('\n').nth(line.checked_sub(1)?)?;
+    let (snippet, caret) = match snippet.strip_prefix("...") {
+        Some(snippet) => (snippet, caret.checked_sub(3)?),
+        None => (snippet, caret),
+    };
+    let snippet = snippet.strip_suffix("...").unwrap_or(snippet);
+    let column = query_line.find(snippet).unwrap_or(0) + caret;
+
+    Some(Position {
+        line,
+        column: column.min(query_line.len()),
+    })
+}
@@ -0,0 +1,202 @@
+use crate::dom::Region;
+use crate::jse;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::thread_local;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Click,
+    Input,
+    Change,
+    KeyDown,
+    Submit,
+}
+impl EventKind {
+    pub const ALL: [EventKind; 5] = [
+        EventKind::Click,
+        EventKind::Input,
+        EventKind::Change,
+        EventKind::KeyDown,
+        EventKind::Submit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Click => "click",
+            EventKind::Input => "input",
+            EventKind::Change => "change",
+            EventKind::KeyDown => "keydown",
+            EventKind::Submit => "submit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+}
+
+/// A DOM event, with its payload depending on the kind: the form's fields
+/// url encoded for submits, the key with any `Ctrl+`, `Alt+` and `Shift+`
+/// prefixes for keydowns, and the element's value otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub payload: String,
+}
+impl Event {
+    /// The submitted form's fields, in order
+    pub fn fields(&self) -> Vec<(String, String)> {
+        self.payload
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let name = decode(parts.next().unwrap_or_default());
+                let value = decode(parts.next().unwrap_or_default());
+
+                (name, value)
+            })
+            .collect()
+    }
+
+    /// Every value submitted for `name`, as a multiple select sends one
+    /// per selected option
+    pub fn values(&self, name: &str) -> Vec<String> {
+        self.fields()
+            .into_iter()
+            .filter(|(field, _)| field == name)
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    pub fn field(&self, name: &str) -> Option<String> {
+        self.values(name).into_iter().next()
+    }
+}
+
+/// Decodes `application/x-www-form-urlencoded` text
+fn decode(text: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' if rest.len() >= 2 => {
+                match u8::from_str_radix(&String::from_utf8_lossy(&rest[..2]), 16) {
+                    Ok(decoded) => {
+                        bytes.push(decoded);
+                        rest = &rest[2..];
+                    }
+                    Err(_) => bytes.push(byte),
+                }
+            }
+            byte => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Returns whether it handled the event, which for keydowns means the
+/// browser shouldn't
+type Handler = Rc<dyn Fn(&Event) -> bool>;
+
+struct Registered {
+    handler: Handler,
+    /// Where the markup calling it was patched in, `None` until it is
+    region: Option<Region>,
+}
+
+thread_local! {
+    static HANDLERS: RefCell<HashMap<String, Registered>> = RefCell::new(HashMap::new());
+}
+
+/// Registers `handler` under `id`, replacing whatever was there, and gives
+/// back the attribute value that routes `kind` events to it, as in
+/// `onclick={on("sort-0", EventKind::Click, ...)}`
+pub fn on(id: &str, kind: EventKind, handler: impl Fn(&Event) + 'static) -> String {
+    register(id, kind, move |event| {
+        handler(event);
+        true
+    })
+}
+
+/// Like `on` for keydowns, with `handler` returning whether it used the
+/// key, so the rest keep their default behaviour
+pub fn on_key(id: &str, handler: impl Fn(&Event) -> bool + 'static) -> String {
+    register(id, EventKind::KeyDown, handler)
+}
+
+fn register(id: &str, kind: EventKind, handler: impl Fn(&Event) -> bool + 'static) -> String {
+    HANDLERS.with(|handlers| {
+        handlers.borrow_mut().insert(
+            id.to_string(),
+            Registered {
+                handler: Rc::new(handler),
+                region: None,
+            },
+        )
+    });
+
+    format!("Module.dispatch({:?}, '{}', this, event)", id, kind.name())
+}
+
+/// Hands the handlers registered since the last patch to `region`, and
+/// drops the ones its previous markup used
+pub fn claim(region: Region) {
+    HANDLERS.with(|handlers| {
+        let mut handlers = handlers.borrow_mut();
+
+        handlers.retain(|_, registered| registered.region != Some(region));
+        for registered in handlers.values_mut() {
+            registered.region.get_or_insert(region);
+        }
+    });
+}
+
+/// Runs the handler registered under `id`, returning whether it handled the
+/// event. The registry isn't borrowed while it runs, so it can re-render
+/// and register more
+pub fn dispatch(id: &str, event: &Event) -> bool {
+    let handler = HANDLERS.with(|handlers| {
+        handlers
+            .borrow()
+            .get(id)
+            .map(|registered| registered.handler.clone())
+    });
+
+    match handler {
+        Some(handler) => handler(event),
+        None => false,
+    }
+}
+
+/// Defines `Module.dispatch`, which the attributes from `on` call to build
+/// the payload and pass it to the `dispatch` export
+pub fn install() -> i32 {
+    jse!(
+        b"
+        Module.dispatch = function (id, kind, element, event) {
+            var payload;
+            if (kind === 'submit') {
+                event.preventDefault();
+                payload = new URLSearchParams(new FormData(element)).toString();
+            } else if (kind === 'keydown') {
+                payload = (event.ctrlKey || event.metaKey ? 'Ctrl+' : '')
+                    + (event.altKey ? 'Alt+' : '')
+                    + (event.shiftKey ? 'Shift+' : '')
+                    + event.key;
+            } else {
+                if (kind === 'click') { event.preventDefault(); }
+                payload = element.value === undefined ? '' : String(element.value);
+            }
+
+            var handled = Module.ccall('dispatch', 'number', ['string', 'string', 'string'], [id, kind, payload]);
+            if (handled && kind === 'keydown') { event.preventDefault(); }
+        };
+    \x00"
+    )
+}
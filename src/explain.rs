@@ -0,0 +1,183 @@
+use crate::data::ResultData;
+
+/// An operator box from duckdb's rendered plan tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanNode {
+    pub name: String,
+    pub details: Vec<String>,
+    pub cardinality: Option<u64>,
+    pub timing: Option<String>,
+    pub children: Vec<PlanNode>,
+}
+
+/// One row of `explain` output, eg the `physical_plan`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub label: String,
+    pub header: Vec<String>,
+    pub roots: Vec<PlanNode>,
+}
+
+#[derive(Debug)]
+struct RawBox {
+    top: usize,
+    bottom: usize,
+    left: usize,
+    right: usize,
+    lines: Vec<String>,
+}
+impl RawBox {
+    fn contains(&self, other: &RawBox) -> bool {
+        self.top < other.top
+            && other.bottom < self.bottom
+            && self.left < other.left
+            && other.right < self.right
+    }
+
+    fn into_node(self) -> PlanNode {
+        let mut sections: Vec<Vec<String>> = vec![vec![]];
+        for line in self.lines {
+            if !line.is_empty() && line.chars().all(|c| c == '─' || c == ' ') {
+                sections.push(vec![]);
+            } else if !line.is_empty() {
+                sections.last_mut().expect("section").push(line);
+            }
+        }
+
+        let mut sections = sections.into_iter().flatten();
+        let name = sections.next().unwrap_or_default();
+
+        let mut node = PlanNode {
+            name,
+            details: vec![],
+            cardinality: None,
+            timing: None,
+            children: vec![],
+        };
+        for line in sections {
+            if let Ok(cardinality) = line.parse() {
+                node.cardinality = Some(cardinality);
+            } else if line.starts_with('(') && line.ends_with("s)") {
+                node.timing = Some(line[1..line.len() - 1].to_string());
+            } else {
+                node.details.push(line);
+            }
+        }
+
+        node
+    }
+}
+
+/// Whether `data` is the output of an `explain` statement
+pub fn is_explain(data: &dyn ResultData) -> bool {
+    let name = |col| data.column_info(col).name;
+
+    data.column_count() == 2 && name(0) == "explain_key" && name(1) == "explain_value"
+}
+
+pub fn from_result(data: &dyn ResultData) -> Result<Vec<Plan>, Box<dyn std::error::Error>> {
+    (0..data.row_count())
+        .map(|row| {
+            let label = data.value(0, row)?.to_string();
+            let text = data.value(1, row)?.to_string();
+
+            Ok(parse(label, &text))
+        })
+        .collect()
+}
+
+/// Rebuilds the operator tree from duckdb's box drawing.
+///
+/// Children are drawn on the row of boxes below their parent, starting in
+/// the parent's column and spreading to the right, so each box belongs to
+/// the closest box above it that starts at or to the left of it.
+pub fn parse(label: String, text: &str) -> Plan {
+    let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+
+    let mut boxes = vec![];
+    for (top, line) in lines.iter().enumerate() {
+        for (left, _) in line.iter().enumerate().filter(|(_, c)| **c == '┌') {
+            let right = match line[left..].iter().position(|c| *c == '┐') {
+                Some(offset) => left + offset,
+                None => continue,
+            };
+            let bottom = match (top + 1..lines.len())
+                .find(|idx| lines[*idx].get(left).map_or(false, |c| *c == '└'))
+            {
+                Some(bottom) => bottom,
+                None => continue,
+            };
+
+            let content = (top + 1..bottom)
+                .map(|idx| {
+                    let line = &lines[idx];
+                    let end = right.min(line.len());
+                    let start = (left + 1).min(end);
+
+                    line[start..end]
+                        .iter()
+                        .collect::<String>()
+                        .trim()
+                        .to_string()
+                })
+                .collect();
+
+            boxes.push(RawBox {
+                top,
+                bottom,
+                left,
+                right,
+                lines: content,
+            });
+        }
+    }
+
+    // the profiler wraps its headings in nested boxes
+    let nesting: Vec<(bool, bool)> = boxes
+        .iter()
+        .map(|raw| {
+            (
+                boxes.iter().any(|other| other.contains(raw)),
+                boxes.iter().any(|other| raw.contains(other)),
+            )
+        })
+        .collect();
+
+    let mut header = vec![];
+    let mut levels: Vec<(usize, Vec<(usize, PlanNode)>)> = vec![];
+    for (raw, (contained, container)) in boxes.into_iter().zip(nesting) {
+        if contained {
+            header.extend(raw.lines.into_iter().filter(|l| !l.is_empty()));
+        } else if !container {
+            match levels.last_mut() {
+                Some((top, level)) if *top == raw.top => level.push((raw.left, raw.into_node())),
+                _ => levels.push((raw.top, vec![(raw.left, raw.into_node())])),
+            }
+        }
+    }
+    let mut levels: Vec<Vec<(usize, PlanNode)>> =
+        levels.into_iter().map(|(_, level)| level).collect();
+
+    while levels.len() > 1 {
+        let children = levels.pop().expect("level");
+        let parents = levels.last_mut().expect("level");
+
+        for (left, child) in children {
+            match parents.iter_mut().rev().find(|(x, _)| *x <= left) {
+                Some((_, parent)) => parent.children.push(child),
+                None => parents.push((left, child)),
+            }
+        }
+    }
+
+    Plan {
+        label,
+        header,
+        roots: levels
+            .pop()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, node)| node)
+            .collect(),
+    }
+}
@@ -0,0 +1,269 @@
+use crate::jse;
+use crate::promise::{self, Promise};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::thread_local;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory uploaded files are written into
+pub const UPLOAD_DIR: &str = "/";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Csv,
+    Parquet,
+}
+impl DataFormat {
+    pub fn detect(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+
+        match extension.as_str() {
+            "csv" | "tsv" => Some(DataFormat::Csv),
+            "parquet" => Some(DataFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DataFormat::Csv => "csv",
+            DataFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Query that reads the whole file
+    pub fn query(self, path: &str) -> String {
+        let path = path.replace('\'', "''");
+
+        match self {
+            DataFormat::Csv => format!("select * from read_csv_auto('{}')", path),
+            DataFormat::Parquet => format!("select * from read_parquet('{}')", path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    Directory,
+    File,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub format: Option<DataFormat>,
+}
+impl FileEntry {
+    pub fn new(path: PathBuf) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(&path)?;
+
+        let kind = if metadata.is_dir() {
+            EntryKind::Directory
+        } else if metadata.is_file() {
+            EntryKind::File
+        } else {
+            EntryKind::Other
+        };
+        let format = match kind {
+            EntryKind::File => DataFormat::detect(&path),
+            _ => None,
+        };
+
+        Ok(Self {
+            kind,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            format,
+            path,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || self.path.to_string_lossy().to_string(),
+            |name| name.to_string_lossy().to_string(),
+        )
+    }
+}
+
+/// Entries of `dir`, directories first. Entries that can't be read are
+/// returned as errors rather than failing the whole listing
+pub fn list(dir: &Path) -> std::io::Result<Vec<Result<FileEntry, ListingError>>> {
+    let mut entries: Vec<Result<FileEntry, ListingError>> = std::fs::read_dir(dir)?
+        .map(|entry| {
+            let path = entry
+                .map_err(|error| ListingError { path: None, error })?
+                .path();
+
+            FileEntry::new(path.clone()).map_err(|error| ListingError {
+                path: Some(path),
+                error,
+            })
+        })
+        .collect();
+
+    entries.sort_by_key(|entry| match entry {
+        Ok(entry) => (entry.kind != EntryKind::Directory, entry.path.clone()),
+        Err(error) => (true, error.path.clone().unwrap_or_default()),
+    });
+
+    Ok(entries)
+}
+
+#[derive(Debug)]
+pub struct ListingError {
+    pub path: Option<PathBuf>,
+    pub error: std::io::Error,
+}
+impl std::fmt::Display for ListingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path.to_string_lossy(), self.error),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+impl std::error::Error for ListingError {}
+
+thread_local! {
+    static CURRENT_DIR: RefCell<PathBuf> = RefCell::new(PathBuf::from(UPLOAD_DIR));
+}
+
+pub fn current_dir() -> PathBuf {
+    CURRENT_DIR.with(|dir| dir.borrow().clone())
+}
+
+pub fn set_current_dir(path: PathBuf) {
+    CURRENT_DIR.with(|dir| dir.replace(path));
+}
+
+pub fn remove(path: &Path) -> std::io::Result<()> {
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Renames `from`, treating a bare file name in `to` as relative to the
+/// directory `from` lives in
+pub fn rename(from: &Path, to: &str) -> std::io::Result<()> {
+    let to = match from.parent() {
+        Some(parent) if !to.starts_with('/') => parent.join(to),
+        _ => PathBuf::from(to),
+    };
+
+    std::fs::rename(from, to)
+}
+
+/// Formats as `YYYY-MM-DD HH:MM:SS` in UTC
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => -(error.duration().as_secs() as i64),
+    };
+    let days = seconds.div_euclid(86400);
+    let remainder = seconds.rem_euclid(86400);
+
+    // civil_from_days, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:0>4}-{:0>2}-{:0>2} {:0>2}:{:0>2}:{:0>2}",
+        year,
+        month,
+        day,
+        remainder / 3600,
+        remainder % 3600 / 60,
+        remainder % 60
+    )
+}
+
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Defines `Module.uploadFiles`, which holds on to a `FileList` and passes
+/// how many files it has to `files_dropped`
+pub fn install_upload_handler() -> i32 {
+    jse!(
+        b"
+        Module.uploadFiles = function (files) {
+            Module.uploads = Array.from(files);
+            Module.ccall('files_dropped', 'void', ['number'], [Module.uploads.length]);
+        };
+    \x00"
+    )
+}
+
+/// Reads the files last passed to `Module.uploadFiles` into `UPLOAD_DIR`.
+/// Every read starts before the first is awaited, so files dropped in the
+/// meantime don't get mixed in. Files duckdb has no reader for are skipped,
+/// and named in the error
+pub async fn save_uploads(count: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rejected = vec![];
+    let reads: Vec<(String, Promise)> = (0..count)
+        .filter_map(|idx| {
+            let name = jse!(
+                -> String,
+                b"return allocateUTF8(Module.uploads[$0].name);\x00",
+                idx
+            );
+            if DataFormat::detect(Path::new(&name)).is_none() {
+                rejected.push(name);
+                return None;
+            }
+
+            let read = promise::start(|id| {
+                jse!(
+                    b"Module.settle($0, Module.uploads[$1].arrayBuffer());\x00",
+                    id,
+                    idx
+                )
+            });
+
+            Some((name, read))
+        })
+        .collect();
+
+    for (name, read) in reads {
+        let contents = read.await?.unwrap_or_default();
+
+        std::fs::write(Path::new(UPLOAD_DIR).join(name), contents)?;
+    }
+
+    if !rejected.is_empty() {
+        return Err(format!(
+            "Only CSV and Parquet files can be uploaded, skipped {}",
+            rejected.join(", ")
+        )
+        .into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use crate::jse;
+use std::cell::RefCell;
+use std::thread_local;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How many queries are kept, oldest are dropped first
+const LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub timestamp: SystemTime,
+    pub duration: Duration,
+    pub success: bool,
+}
+impl HistoryEntry {
+    fn encode(&self) -> String {
+        let seconds = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let query = self
+            .query
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n");
+
+        format!(
+            "{}\t{}\t{}\t{}",
+            seconds,
+            self.duration.as_micros(),
+            if self.success { 1 } else { 0 },
+            query
+        )
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(4, '\t');
+        let seconds = fields.next()?.parse().ok()?;
+        let micros = fields.next()?.parse().ok()?;
+        let success = fields.next()? == "1";
+
+        let mut query = String::new();
+        let mut chars = fields.next()?.chars();
+        while let Some(c) = chars.next() {
+            query.push(if c == '\\' {
+                match chars.next()? {
+                    't' => '\t',
+                    'n' => '\n',
+                    other => other,
+                }
+            } else {
+                c
+            });
+        }
+
+        Some(Self {
+            query,
+            timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+            duration: Duration::from_micros(micros),
+            success,
+        })
+    }
+}
+
+pub fn encode(entries: &[HistoryEntry]) -> String {
+    entries
+        .iter()
+        .map(HistoryEntry::encode)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn decode(stored: &str) -> Vec<HistoryEntry> {
+    stored.lines().filter_map(HistoryEntry::decode).collect()
+}
+
+fn load() -> Vec<HistoryEntry> {
+    let stored = jse!(
+        -> Option<String>,
+        b"
+        if (typeof localStorage === 'undefined') { return 0; }
+        var history = localStorage.getItem('history');
+        return history === null ? 0 : allocateUTF8(history);
+    \x00"
+    );
+
+    stored.map_or_else(Vec::new, |stored| decode(&stored))
+}
+
+fn save(entries: &[HistoryEntry]) -> i32 {
+    jse!(
+        b"if (typeof localStorage !== 'undefined') { localStorage.setItem('history', UTF8ToString($0, $1)); }\x00",
+        &encode(entries)
+    )
+}
+
+thread_local! {
+    static HISTORY: RefCell<Option<Vec<HistoryEntry>>> = RefCell::new(None);
+}
+
+/// Past queries, oldest first
+pub fn entries() -> Vec<HistoryEntry> {
+    HISTORY.with(|history| history.borrow_mut().get_or_insert_with(load).clone())
+}
+
+pub fn record(entry: HistoryEntry) {
+    HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        let entries = history.get_or_insert_with(load);
+
+        entries.push(entry);
+        if entries.len() > LIMIT {
+            entries.drain(..entries.len() - LIMIT);
+        }
+
+        save(entries);
+    });
+}
+
+pub fn clear() {
+    HISTORY.with(|history| history.replace(Some(vec![])));
+    save(&[]);
+}
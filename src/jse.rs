@@ -1,45 +1,178 @@
-use lazy_static::*;
+use std::ffi::CString;
 
-lazy_static! {
-    pub static ref EMPTY_SIG: std::ffi::CString =
-        unsafe { std::ffi::CString::from_vec_unchecked(vec![]) };
+extern "C" {
+    fn emscripten_asm_const_int(code: *const u8, sig_ptr: *const u8, arg_buf: *const u8)
+        -> *mut u8;
+
+    fn emscripten_asm_const_double(code: *const u8, sig_ptr: *const u8, arg_buf: *const u8) -> f64;
 }
 
-#[macro_export]
-macro_rules! jse {
-    ($js_expr:expr, $( $i:ident ),*) => {
-        {
-            const LEN: usize = count_tts::count_tts!($($i)*);
+/// Takes ownership of a string a snippet returned with `allocateUTF8`, where
+/// `0` stands for no string
+///
+/// # Safety
+/// `ptr` must be `0` or come from `allocateUTF8`, and not be used again
+pub unsafe fn take_string(ptr: i32) -> Option<String> {
+    if ptr == 0 {
+        return None;
+    }
 
-            #[repr(C, align(16))]
-            struct AlignToSixteen([i32; LEN]);
+    let string = std::ffi::CStr::from_ptr(ptr as *const crate::c_char)
+        .to_string_lossy()
+        .to_string();
+    libc::free(ptr as *mut libc::c_void);
 
-            let array = &AlignToSixteen([$($i,)*]);
-            let sig = CString::new("i".repeat(LEN)).expect("sig");
-            const SNIPPET: &'static [u8] = $js_expr;
+    Some(string)
+}
 
-            assert_eq!(SNIPPET[..].last().expect("empty snippet?"), &0);
+/// The signature and argument buffer emscripten reads a snippet's `$0`,
+/// `$1`, ... from. Pointers in it borrow from the arguments, so it only
+/// lives for the duration of one `jse!`
+#[derive(Debug, Default)]
+pub struct Args {
+    sig: Vec<u8>,
+    slots: Vec<u32>,
+}
+impl Args {
+    fn int(&mut self, value: i32) {
+        self.sig.push(b'i');
+        self.slots.push(value as u32);
+    }
 
-            unsafe {
-                emscripten_asm_const_int(
-                    SNIPPET as *const _ as *const u8,
-                    sig.as_ptr() as *const _ as *const u8,
-                    array as *const _ as *const u8,
-                ) as i32
-            }
+    fn double(&mut self, value: f64) {
+        // doubles are read from 8 byte aligned slots
+        if self.slots.len() % 2 == 1 {
+            self.slots.push(0);
         }
-    };
-    ($js_expr:expr) => {
+        let bits = value.to_bits();
+
+        self.sig.push(b'd');
+        self.slots.push(bits as u32);
+        self.slots.push((bits >> 32) as u32);
+    }
+
+    fn call<R>(
+        &self,
+        snippet: &[u8],
+        asm_const: unsafe extern "C" fn(*const u8, *const u8, *const u8) -> R,
+    ) -> R {
+        assert_eq!(snippet.last().expect("empty snippet?"), &0);
+
+        let sig = CString::new(self.sig.clone()).expect("sig");
+        let buffer: Vec<u64> = self
+            .slots
+            .chunks(2)
+            .map(|pair| u64::from(pair[0]) | pair.get(1).map_or(0, |high| u64::from(*high) << 32))
+            .collect();
+
+        unsafe {
+            asm_const(
+                snippet.as_ptr(),
+                sig.as_ptr() as *const u8,
+                buffer.as_ptr() as *const u8,
+            )
+        }
+    }
+}
+
+/// Something that can be passed to a snippet. Strings and slices take two
+/// placeholders, a pointer then a length in bytes, so they're read with
+/// `UTF8ToString($0, $1)` or `HEAPU8.subarray($0, $0 + $1)`
+pub trait JsArg {
+    fn push(self, args: &mut Args);
+}
+impl JsArg for i32 {
+    fn push(self, args: &mut Args) {
+        args.int(self);
+    }
+}
+impl JsArg for u32 {
+    fn push(self, args: &mut Args) {
+        args.int(self as i32);
+    }
+}
+impl JsArg for bool {
+    fn push(self, args: &mut Args) {
+        args.int(self.into());
+    }
+}
+impl JsArg for f64 {
+    fn push(self, args: &mut Args) {
+        args.double(self);
+    }
+}
+impl JsArg for &[u8] {
+    fn push(self, args: &mut Args) {
+        args.int(self.as_ptr() as i32);
+        args.int(self.len() as i32);
+    }
+}
+impl JsArg for &str {
+    fn push(self, args: &mut Args) {
+        self.as_bytes().push(args);
+    }
+}
+impl JsArg for &String {
+    fn push(self, args: &mut Args) {
+        self.as_str().push(args);
+    }
+}
+
+/// What a snippet's `return` is converted to. Strings must be returned
+/// with `allocateUTF8`, and are freed once copied out
+pub trait JsReturn {
+    fn call(snippet: &[u8], args: &Args) -> Self;
+}
+impl JsReturn for i32 {
+    fn call(snippet: &[u8], args: &Args) -> Self {
+        args.call(snippet, emscripten_asm_const_int) as i32
+    }
+}
+impl JsReturn for () {
+    fn call(snippet: &[u8], args: &Args) -> Self {
+        i32::call(snippet, args);
+    }
+}
+impl JsReturn for bool {
+    fn call(snippet: &[u8], args: &Args) -> Self {
+        i32::call(snippet, args) != 0
+    }
+}
+impl JsReturn for f64 {
+    fn call(snippet: &[u8], args: &Args) -> Self {
+        args.call(snippet, emscripten_asm_const_double)
+    }
+}
+/// `0` or `null` for `None`
+impl JsReturn for Option<String> {
+    fn call(snippet: &[u8], args: &Args) -> Self {
+        // snippets asked for a string return one from allocateUTF8
+        unsafe { take_string(i32::call(snippet, args)) }
+    }
+}
+impl JsReturn for String {
+    fn call(snippet: &[u8], args: &Args) -> Self {
+        Option::<String>::call(snippet, args).unwrap_or_default()
+    }
+}
+
+/// Runs a nul terminated javascript snippet, with arguments as `$0`, `$1`
+/// and so on. Returns an `i32` unless another `JsReturn` is asked for
+/// with `jse!(-> String, b"...\x00", ...)`
+#[macro_export]
+macro_rules! jse {
+    (-> $ret:ty, $js_expr:expr $(, $arg:expr)* $(,)?) => {
         {
             const SNIPPET: &'static [u8] = $js_expr;
 
-            unsafe {
-                emscripten_asm_const_int(
-                    SNIPPET as *const _ as *const u8,
-                    crate::jse::EMPTY_SIG.as_ptr() as *const _ as *const u8,
-                    std::ptr::null() as *const _ as *const u8,
-                ) as i32
-            }
+            #[allow(unused_mut)]
+            let mut args = $crate::jse::Args::default();
+            $( $crate::jse::JsArg::push($arg, &mut args); )*
+
+            <$ret as $crate::jse::JsReturn>::call(SNIPPET, &args)
         }
     };
+    ($js_expr:expr $(, $arg:expr)* $(,)?) => {
+        $crate::jse!(-> i32, $js_expr $(, $arg)*)
+    };
 }
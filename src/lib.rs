@@ -0,0 +1,297 @@
+//! DuckDB's C api wrapped for Rust: databases, connections and their
+//! results. Builds for the browser and natively, see `bin.rs` for the page
+#![feature(debug_non_exhaustive)]
+#![feature(extern_types)]
+#![feature(try_trait)]
+#![feature(static_nobundle)]
+
+use crate::state::DuckDBState;
+use crate::types::{
+    duckdb_blob, duckdb_connection, duckdb_database, duckdb_date, duckdb_hugeint, duckdb_interval,
+    duckdb_time, duckdb_timestamp, duckdb_type as DuckDBType, type_name, DuckDBColumn,
+    DuckDBResult,
+};
+use libc::c_void;
+use std::convert::{TryFrom, TryInto};
+use std::ffi::CStr;
+pub use std::os::raw::c_char;
+use std::time::Duration;
+use strum_macros::IntoStaticStr;
+
+mod bindings;
+pub mod data;
+pub mod db;
+pub mod error;
+pub mod pool;
+pub mod sql;
+pub mod state;
+pub mod types;
+
+#[derive(Debug, Clone, IntoStaticStr)]
+pub enum DbType {
+    Boolean(bool),
+    Tinyint(i8),
+    Smallint(i16),
+    Integer(i32),
+    Bigint(i64),
+    Float(f32),
+    Date(duckdb_date),
+    Time(duckdb_time),
+    Timestamp(duckdb_timestamp),
+    Double(f64),
+    String(String),
+    Interval(duckdb_interval),
+    Hugeint(duckdb_hugeint),
+    Blob(duckdb_blob),
+    Unknown(DuckDBType),
+}
+impl ToString for DbType {
+    fn to_string(&self) -> String {
+        use crate::DbType::*;
+
+        let value: &dyn ToString = match self {
+            Boolean(s) => s,
+            Tinyint(s) => s,
+            Smallint(s) => s,
+            Integer(i) => i,
+            Bigint(s) => s,
+            Float(f) => f,
+            Double(f) => f,
+            String(s) => s,
+            Time(s) => s,
+            Timestamp(s) => s,
+            Date(s) => s,
+            Blob(s) => s,
+            Hugeint(s) => s,
+            Interval(s) => s,
+            Unknown(_) => &"unknown",
+        };
+
+        value.to_string()
+    }
+}
+
+// declared with the pointer types we pass, rather than bindgen's
+#[allow(clashing_extern_declarations)]
+extern "C" {
+    fn duckdb_open(path: *const c_char, database: *const duckdb_database) -> DuckDBState;
+
+    fn duckdb_disconnect(con: *const duckdb_connection);
+
+    fn ext_duckdb_close(db: *const duckdb_database);
+
+    fn duckdb_destroy_result(result: *const DuckDBResult);
+
+    /// Converts the specified value to a bool. Returns false on failure or NULL.
+    fn duckdb_value_boolean(result: *const DuckDBResult, col: u64, row: u64) -> bool;
+    /// Converts the specified value to an int8_t. Returns 0 on failure or NULL.
+    fn duckdb_value_int8(result: *const DuckDBResult, col: u64, row: u64) -> i8;
+    /// Converts the specified value to an int16_t. Returns 0 on failure or NULL.
+    fn duckdb_value_int16(result: *const DuckDBResult, col: u64, row: u64) -> i16;
+    /// Converts the specified value to an int64_t. Returns 0 on failure or NULL.
+    fn duckdb_value_int32(result: *const DuckDBResult, col: u64, row: u64) -> i32;
+    /// Converts the specified value to an int64_t. Returns 0 on failure or NULL.
+    fn duckdb_value_int64(result: *const DuckDBResult, col: u64, row: u64) -> i64;
+    /// Converts the specified value to a float. Returns 0.0 on failure or NULL.
+    fn duckdb_value_float(result: *const DuckDBResult, col: u64, row: u64) -> f32;
+    /// Converts the specified value to a double. Returns 0.0 on failure or NULL.
+    fn duckdb_value_double(result: *const DuckDBResult, col: u64, row: u64) -> f64;
+    /// Converts the specified value to a string. Returns nullptr on failure or NULL. The result must be freed with free.
+    fn duckdb_value_varchar(result: *const DuckDBResult, col: u64, row: u64) -> *const c_char;
+    /// Fetches a blob from a result set column. Returns a blob with blob.data set to nullptr on failure or NULL. The
+    /// resulting "blob.data" must be freed with free.
+    fn duckdb_value_blob(result: *const DuckDBResult, blob: *const duckdb_blob, col: u64, row: u64);
+
+    fn duckdb_value_date(result: *const DuckDBResult, col: u64, row: u64) -> *const duckdb_date;
+    fn duckdb_value_time(result: *const DuckDBResult, col: u64, row: u64) -> *const duckdb_time;
+    fn duckdb_value_timestamp(
+        result: *const DuckDBResult,
+        col: u64,
+        row: u64,
+    ) -> *const duckdb_timestamp;
+
+    fn duckdb_value_hugeint(
+        result: *const DuckDBResult,
+        col: u64,
+        row: u64,
+    ) -> *const duckdb_hugeint;
+    fn duckdb_value_interval(
+        result: *const DuckDBResult,
+        col: u64,
+        row: u64,
+    ) -> *const duckdb_interval;
+
+    pub fn mallocy(size: usize) -> *const c_void;
+}
+
+/// Zeroed space for a `T` for DuckDB to fill in, sized for the target
+/// rather than assuming 32 bit pointers
+fn malloc<T: Sized>() -> *const T {
+    unsafe { mallocy(std::mem::size_of::<T>()) as *const T }
+}
+
+#[derive(Debug)]
+pub struct ResolvedResult<'a> {
+    result: *const DuckDBResult,
+    resolved: &'a DuckDBResult,
+    columns: &'a [DuckDBColumn],
+    length: usize,
+    execution: Duration,
+    rows_affected: Option<i64>,
+    logical_types: Vec<String>,
+}
+
+/// Metadata about a result column that doesn't depend on any of its rows
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub type_: DuckDBType,
+    /// Full type, eg `DECIMAL(18,3)` where the physical type is `DOUBLE`
+    pub sql_type: String,
+}
+impl<'a> Drop for ResolvedResult<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            duckdb_destroy_result(self.result);
+            libc::free(self.result as *mut c_void);
+        }
+    }
+}
+impl<'a> ResolvedResult<'a> {
+    unsafe fn new(result: *const DuckDBResult) -> Self {
+        let resolved = &*result;
+
+        let length = resolved.column_count.try_into().expect("Too many columns");
+        // owned by the result, and freed with it
+        let columns = std::slice::from_raw_parts(resolved.columns, length);
+
+        Self {
+            result,
+            resolved,
+            columns,
+            length,
+            execution: Duration::default(),
+            rows_affected: None,
+            logical_types: vec![],
+        }
+    }
+
+    pub fn column_count(&self) -> u64 {
+        self.resolved.column_count
+    }
+
+    pub fn column_info(&self, col: u64) -> ColumnInfo {
+        let column = self.column(col);
+        let idx = <usize as TryFrom<u64>>::try_from(col).expect("Too big");
+
+        let sql_type = match self.logical_types.get(idx) {
+            Some(name) if self.logical_types.len() == self.length => name.clone(),
+            _ => type_name(column.type_).to_string(),
+        };
+
+        ColumnInfo {
+            name: unsafe { CStr::from_ptr(column.name) }
+                .to_string_lossy()
+                .to_string(),
+            type_: column.type_,
+            sql_type,
+        }
+    }
+
+    pub fn row_count(&self) -> u64 {
+        self.resolved.row_count
+    }
+
+    /// How long duckdb took to run the query
+    pub fn execution_time(&self) -> Duration {
+        self.execution
+    }
+
+    /// Number of rows changed, if the query was an insert, update or delete
+    pub fn rows_affected(&self) -> Option<i64> {
+        self.rows_affected
+    }
+
+    pub fn is_null(&self, col: u64, row: u64) -> bool {
+        let column = self.column(col);
+
+        row < self.row_count()
+            && !column.nullmask.is_null()
+            && unsafe { *column.nullmask.add(row as usize) }
+    }
+
+    fn column(&self, col: u64) -> &DuckDBColumn {
+        &self.columns[<usize as TryFrom<u64>>::try_from(col).expect("Too big")]
+    }
+
+    pub fn consume(&self, col: u64, row: u64) -> Result<DbType, Box<dyn std::error::Error>> {
+        let column: &DuckDBColumn = self.column(col);
+        let result = self.result;
+
+        Ok(unsafe {
+            match &column.type_ {
+                DuckDBType::DUCKDB_TYPE_BOOLEAN => {
+                    DbType::Boolean(duckdb_value_boolean(result, col, row))
+                }
+                DuckDBType::DUCKDB_TYPE_TINYINT => {
+                    DbType::Tinyint(duckdb_value_int8(result, col, row))
+                }
+                DuckDBType::DUCKDB_TYPE_SMALLINT => {
+                    DbType::Smallint(duckdb_value_int16(result, col, row))
+                }
+                DuckDBType::DUCKDB_TYPE_INTEGER => {
+                    DbType::Integer(duckdb_value_int32(result, col, row))
+                }
+                DuckDBType::DUCKDB_TYPE_BIGINT => {
+                    DbType::Bigint(duckdb_value_int64(result, col, row))
+                }
+                DuckDBType::DUCKDB_TYPE_TIME => {
+                    DbType::Time(*duckdb_value_time(result, col, row).as_ref().expect("Time"))
+                }
+                DuckDBType::DUCKDB_TYPE_TIMESTAMP => DbType::Timestamp(
+                    *duckdb_value_timestamp(result, col, row)
+                        .as_ref()
+                        .expect("Timestamp"),
+                ),
+                DuckDBType::DUCKDB_TYPE_DATE => {
+                    DbType::Date(*duckdb_value_date(result, col, row).as_ref().expect("Date"))
+                }
+                DuckDBType::DUCKDB_TYPE_FLOAT => {
+                    DbType::Float(duckdb_value_float(result, col, row))
+                }
+                DuckDBType::DUCKDB_TYPE_DOUBLE => {
+                    DbType::Double(duckdb_value_double(result, col, row))
+                }
+                DuckDBType::DUCKDB_TYPE_VARCHAR => {
+                    let ptr = duckdb_value_varchar(result, col, row);
+                    if ptr.is_null() {
+                        return Ok(DbType::String(String::new()));
+                    }
+                    let string = CStr::from_ptr(ptr).to_string_lossy().to_string();
+                    libc::free(ptr as *mut c_void);
+
+                    DbType::String(string)
+                }
+                DuckDBType::DUCKDB_TYPE_HUGEINT => DbType::Hugeint(
+                    *duckdb_value_hugeint(result, col, row)
+                        .as_ref()
+                        .expect("Hugeint"),
+                ),
+                DuckDBType::DUCKDB_TYPE_BLOB => {
+                    let ptr: *const duckdb_blob = malloc();
+                    duckdb_value_blob(result, ptr, col, row);
+                    let blob = std::ptr::read(ptr.as_ref().expect("Blob"));
+                    libc::free(ptr as *mut c_void);
+
+                    DbType::Blob(blob)
+                }
+                DuckDBType::DUCKDB_TYPE_INTERVAL => DbType::Interval(
+                    *duckdb_value_interval(result, col, row)
+                        .as_ref()
+                        .expect("Interval"),
+                ),
+                _ => DbType::Unknown(column.type_),
+            }
+        })
+    }
+}
@@ -0,0 +1,82 @@
+use crate::jse;
+use crate::promise;
+use std::io::ErrorKind;
+
+/// The database file and its write-ahead log, which only exists until the
+/// next checkpoint
+fn files(path: &str) -> [String; 2] {
+    [path.to_string(), format!("{}.wal", path)]
+}
+
+/// Defines `Module.idb`, which runs a request against the IndexedDB store
+/// databases are kept in and returns a promise of its result. It's `null`
+/// where there's no IndexedDB, so nothing is restored or saved
+pub fn install() -> i32 {
+    jse!(
+        b"
+        Module.idb = typeof indexedDB === 'undefined' ? null : function (mode, request) {
+            return new Promise(function (resolve, reject) {
+                var open = indexedDB.open('rust_wasm_duckdb', 1);
+                open.onupgradeneeded = function () { open.result.createObjectStore('files'); };
+                open.onerror = function () { reject(open.error); };
+                open.onsuccess = function () {
+                    var transaction = open.result.transaction('files', mode);
+                    var pending = request(transaction.objectStore('files'));
+                    transaction.oncomplete = function () { resolve(pending.result); };
+                    transaction.onerror = function () { reject(transaction.error); };
+                };
+            });
+        };
+    \x00"
+    )
+}
+
+/// Writes the copies of the database at `path` saved by `save` back into
+/// MEMFS, which has to happen before it's opened
+pub async fn restore(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for file in &files(path) {
+        let saved = promise::start(|id| {
+            jse!(
+                b"Module.settle($0, Module.idb && Module.idb('readonly', function (store) { return store.get(UTF8ToString($1, $2)); }));\x00",
+                id,
+                file
+            )
+        })
+        .await?;
+
+        if let Some(contents) = saved {
+            std::fs::write(file, contents)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the database at `path` into IndexedDB, so it survives a reload
+pub async fn save(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for file in &files(path) {
+        let saving = match std::fs::read(file) {
+            Ok(contents) => promise::start(|id| {
+                jse!(
+                    b"Module.settle($0, Module.idb && Module.idb('readwrite', function (store) { return store.put(HEAPU8.slice($3, $3 + $4), UTF8ToString($1, $2)); }));\x00",
+                    id,
+                    file,
+                    &contents[..]
+                )
+            }),
+            // a stale log would be replayed on top of the checkpointed file
+            Err(error) if error.kind() == ErrorKind::NotFound => promise::start(|id| {
+                jse!(
+                    b"Module.settle($0, Module.idb && Module.idb('readwrite', function (store) { return store.delete(UTF8ToString($1, $2)); }));\x00",
+                    id,
+                    file
+                )
+            }),
+            Err(error) => return Err(error.into()),
+        };
+
+        saving.await?;
+    }
+
+    Ok(())
+}
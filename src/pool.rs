@@ -0,0 +1,85 @@
+use crate::db::{Connection, DB};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+struct PoolInner {
+    db: DB,
+    idle: Mutex<Vec<Connection>>,
+    max_idle: usize,
+}
+
+/// Hands out connections to one database, reusing those that are given
+/// back. Clones share the same connections, so each thread can have its own
+#[derive(Debug, Clone)]
+pub struct Pool {
+    inner: Arc<PoolInner>,
+}
+impl Pool {
+    /// Keeps up to `max_idle` connections open between uses
+    pub fn new(db: DB, max_idle: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                db,
+                idle: Mutex::new(Vec::new()),
+                max_idle,
+            }),
+        }
+    }
+
+    /// An idle connection, or a new one if they're all in use
+    pub fn get(&self) -> Result<PooledConnection, Box<dyn std::error::Error>> {
+        let idle = self.inner.idle.lock().expect("poisoned").pop();
+        let connection = match idle {
+            Some(connection) => connection,
+            None => self.inner.db.connection()?,
+        };
+
+        Ok(PooledConnection {
+            connection: Some(connection),
+            pool: self.inner.clone(),
+        })
+    }
+
+    /// How many connections are waiting to be reused
+    pub fn idle(&self) -> usize {
+        self.inner.idle.lock().expect("poisoned").len()
+    }
+}
+
+/// A `Connection` that goes back to its `Pool` when dropped. Its open
+/// transaction is rolled back, its default timeout cleared and the
+/// interrupt handles given out for it stop working. One that created
+/// temporary tables or changed settings like `SET` variables is closed
+/// instead, so the next borrower starts from a clean session
+#[derive(Debug)]
+pub struct PooledConnection {
+    connection: Option<Connection>,
+    pool: Arc<PoolInner>,
+}
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection.as_ref().expect("returned")
+    }
+}
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.connection.as_mut().expect("returned")
+    }
+}
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(mut connection) = self.connection.take() {
+            if connection.reset().is_err() {
+                return;
+            }
+
+            let mut idle = self.pool.idle.lock().expect("poisoned");
+            if idle.len() < self.pool.max_idle {
+                idle.push(connection);
+            }
+        }
+    }
+}
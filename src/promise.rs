@@ -0,0 +1,196 @@
+use crate::jse;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread_local;
+
+/// What a promise settled with, `None` when it resolved to `null` or
+/// `undefined`. Strings are passed as their UTF-8 bytes
+pub type Settled = Result<Option<Vec<u8>>, String>;
+
+#[derive(Default)]
+struct PromiseState {
+    settled: Option<Settled>,
+    waker: Option<Waker>,
+}
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+thread_local! {
+    static NEXT_PROMISE: Cell<i32> = Cell::new(1);
+    static PROMISES: RefCell<HashMap<i32, PromiseState>> = RefCell::new(HashMap::new());
+    static NEXT_TASK: Cell<usize> = Cell::new(0);
+    static TASKS: RefCell<HashMap<usize, Task>> = RefCell::new(HashMap::new());
+    static READY: RefCell<VecDeque<usize>> = RefCell::new(VecDeque::new());
+    static RUNNING: Cell<bool> = Cell::new(false);
+}
+
+/// A javascript promise being awaited from Rust
+#[derive(Debug)]
+pub struct Promise {
+    id: i32,
+}
+impl Future for Promise {
+    type Output = Settled;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        PROMISES.with(|promises| {
+            let mut promises = promises.borrow_mut();
+            let state = promises.entry(self.id).or_default();
+
+            match state.settled.take() {
+                Some(settled) => Poll::Ready(settled),
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+impl Drop for Promise {
+    /// Anything it settles with from now on is thrown away
+    fn drop(&mut self) {
+        PROMISES.with(|promises| promises.borrow_mut().remove(&self.id));
+    }
+}
+
+/// Gives `begin` an id to pass to `Module.settle(id, promise)` from a
+/// snippet, and returns the future of that promise. Values that aren't
+/// promises settle straight away, so work that turns out to be synchronous
+/// finishes without waiting on the event loop
+pub fn start(begin: impl FnOnce(i32) -> i32) -> Promise {
+    let id = NEXT_PROMISE.with(|next| next.replace(next.get() + 1));
+    PROMISES.with(|promises| promises.borrow_mut().insert(id, PromiseState::default()));
+
+    begin(id);
+
+    Promise { id }
+}
+
+/// Copies out a buffer `Module.settle` allocated and frees it, where a
+/// negative length stands for `null`
+///
+/// # Safety
+/// `ptr` must come from `malloc` and hold at least `len` bytes
+pub unsafe fn take_buffer(ptr: *mut u8, len: i32) -> Option<Vec<u8>> {
+    if len < 0 {
+        return None;
+    }
+
+    let bytes = std::slice::from_raw_parts(ptr, len as usize).to_vec();
+    libc::free(ptr as *mut libc::c_void);
+
+    Some(bytes)
+}
+
+/// Wakes whatever is awaiting promise `id`
+pub fn settle(id: i32, settled: Settled) {
+    let waker = PROMISES.with(|promises| {
+        let mut promises = promises.borrow_mut();
+        let state = promises.get_mut(&id)?;
+        state.settled = Some(settled);
+
+        state.waker.take()
+    });
+
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// How many tasks and promises are still waiting
+#[cfg(test)]
+pub fn outstanding() -> (usize, usize) {
+    (
+        TASKS.with(|tasks| tasks.borrow().len()),
+        PROMISES.with(|promises| promises.borrow().len()),
+    )
+}
+
+struct TaskWaker(usize);
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        schedule(self.0);
+    }
+}
+
+/// Runs `future` until it first waits, then again whenever what it's
+/// waiting on is ready
+pub fn spawn(future: impl Future<Output = ()> + 'static) {
+    let id = NEXT_TASK.with(|next| next.replace(next.get() + 1));
+    TASKS.with(|tasks| tasks.borrow_mut().insert(id, Box::pin(future)));
+
+    schedule(id);
+}
+
+fn schedule(id: usize) {
+    READY.with(|ready| ready.borrow_mut().push_back(id));
+
+    // a task woken while another is being polled runs once that returns
+    if RUNNING.with(|running| running.replace(true)) {
+        return;
+    }
+
+    while let Some(id) = READY.with(|ready| ready.borrow_mut().pop_front()) {
+        // taken out while polling, so the task can spawn others, and only
+        // put back if it isn't finished
+        let task = TASKS.with(|tasks| tasks.borrow_mut().remove(&id));
+
+        if let Some(mut task) = task {
+            let waker = Waker::from(Arc::new(TaskWaker(id)));
+
+            if task
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending()
+            {
+                TASKS.with(|tasks| tasks.borrow_mut().insert(id, task));
+            }
+        }
+    }
+
+    RUNNING.with(|running| running.set(false));
+}
+
+/// Defines `Module.settle`, which passes what a promise settles with back
+/// to the `settle_promise` export
+pub fn install() -> i32 {
+    jse!(
+        b"
+        var send = function (id, ok, value) {
+            if (value === null || value === undefined) {
+                Module.ccall('settle_promise', 'void', ['number', 'number', 'number', 'number'], [id, ok, 0, -1]);
+                return;
+            }
+
+            var bytes;
+            if (value instanceof ArrayBuffer) {
+                bytes = new Uint8Array(value);
+            } else if (value instanceof Uint8Array) {
+                bytes = value;
+            } else {
+                bytes = new TextEncoder().encode(String(value));
+            }
+
+            var ptr = _malloc(Math.max(bytes.length, 1));
+            HEAPU8.set(bytes, ptr);
+            Module.ccall('settle_promise', 'void', ['number', 'number', 'number', 'number'], [id, ok, ptr, bytes.length]);
+        };
+
+        Module.settle = function (id, promise) {
+            if (promise && typeof promise.then === 'function') {
+                promise.then(
+                    function (value) { send(id, 1, value); },
+                    function (error) { send(id, 0, error && error.message || String(error)); }
+                );
+            } else {
+                send(id, 1, promise);
+            }
+        };
+    \x00"
+    )
+}
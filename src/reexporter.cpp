@@ -1,24 +1,56 @@
+#ifdef __EMSCRIPTEN__
 #include <emscripten.h>
-#include "duckdb.hpp"
+#endif
+// built together with the amalgamation, to reach its DatabaseData and
+// duckdb_translate_result
+#include "duckdb.cpp"
+// the amalgamated parser grammar leaves its token macros defined
+#undef VARCHAR
+#include <cstring>
 #include <iostream>
 
 using namespace duckdb;
 
-struct DatabaseData {
-  DatabaseData() : database(nullptr) {}
-  ~DatabaseData() {
-    if (database) {
-      delete database;
+// The C api has no type for some columns, like DECIMAL, and asserts on them.
+// Those are cast to one it has before the result is translated: DECIMAL to
+// DOUBLE, anything else to VARCHAR
+static void cast_unsupported(MaterializedQueryResult &result) {
+    vector<LogicalType> types;
+    bool unsupported = false;
+    for (auto &type : result.types) {
+        if (ConvertCPPTypeToC(type) != DUCKDB_TYPE_INVALID) {
+            types.push_back(type);
+        } else {
+            types.push_back(type.id() == LogicalTypeId::DECIMAL ? LogicalType::DOUBLE : LogicalType::VARCHAR);
+            unsupported = true;
+        }
+    }
+    if (!unsupported) {
+        return;
     }
-  }
 
-  duckdb::DuckDB *database;
-};
+    for (auto &chunk : result.collection.Chunks()) {
+        DataChunk cast;
+        cast.Initialize(types);
+        for (idx_t col = 0; col < types.size(); col++) {
+            if (types[col] == result.types[col]) {
+                cast.data[col].Reference(chunk->data[col]);
+            } else {
+                VectorOperations::Cast(chunk->data[col], cast.data[col], chunk->size());
+                cast.data[col].Normalify(chunk->size());
+            }
+        }
+        cast.SetCardinality(*chunk);
+        chunk->Move(cast);
+    }
+    result.collection.Types() = types;
+    result.types = types;
+}
 
 extern "C"
 {
-    void* mallocy() {
-        return calloc(1, sizeof(void*));
+    void* mallocy(size_t size) {
+        return calloc(1, size);
     }
 
     duckdb_date *duckdb_value_date(duckdb_result *result, idx_t col, idx_t row)
@@ -57,4 +89,27 @@ extern "C"
     duckdb_connection create_connection(DatabaseData* db) {
         return (duckdb_connection) new duckdb::Connection(*db->database);
     }
+
+    // duckdb_query, that also hands back the newline separated logical
+    // types of the result's columns, like DECIMAL(18,3) where the C api only
+    // has DOUBLE. They must be freed with free
+    duckdb_state ext_duckdb_query(duckdb_connection connection, const char *query, duckdb_result *out, char **types) {
+        auto result = ((duckdb::Connection *)connection)->Query(query);
+
+        std::string names;
+        for (auto &type : result->types) {
+            names += type.ToString();
+            names += "\n";
+        }
+        *types = strdup(names.c_str());
+        cast_unsupported(*result);
+
+        return duckdb_translate_result(result.get(), out);
+    }
+
+    // Makes the query running on connection fail with an INTERRUPT error.
+    // Only sets a flag, so it's safe to call from anywhere
+    void ext_duckdb_interrupt(duckdb_connection connection) {
+        ((duckdb::Connection *)connection)->Interrupt();
+    }
 }
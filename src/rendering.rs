@@ -1,7 +1,18 @@
-use crate::{DbType, DuckDBColumn, ResolvedResult};
-use render::{component, rsx, Render};
-use std::ffi::CStr;
+use crate::catalog::{Catalog, Schema, TableKind};
+use crate::chart::{self, ChartKind, ChartSpec};
+use crate::data::ResultData;
+use crate::error::{ErrorKind, QueryError};
+use crate::events::{on, on_key, EventKind};
+use crate::explain::{Plan, PlanNode};
+use crate::files::{self, format_time, human_size, DataFormat, EntryKind, FileEntry, ListingError};
+use crate::history::HistoryEntry;
+use crate::view::{Direction, ResultView, Sort};
+use crate::{complete, dom, routing, sql, worker, DbType};
+use render::{component, html, raw, rsx, Render};
 use std::iter::{FromIterator, Map};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Duration;
 
 pub trait Contain<I: Render> {
     fn contain(self) -> Container<I>;
@@ -31,36 +42,191 @@ impl<T: Render> Render for Container<T> {
     }
 }
 
+/// Already rendered markup, written out without escaping
+#[derive(Debug)]
+pub struct Html(pub String);
+impl Render for Html {
+    fn render_into<W: core::fmt::Write>(self, writer: &mut W) -> Result<(), std::fmt::Error> {
+        writer.write_str(&self.0)
+    }
+}
+
 impl Render for DbType {
     fn render_into<W: core::fmt::Write>(self, writer: &mut W) -> Result<(), std::fmt::Error> {
         writer.write_str(&self.to_string())
     }
 }
 
+/// What a table cell shows
+enum Cell {
+    Value(DbType),
+    Null,
+    Unreadable(String),
+}
+impl Render for Cell {
+    fn render_into<W: core::fmt::Write>(self, writer: &mut W) -> Result<(), std::fmt::Error> {
+        match self {
+            Cell::Value(value) => value.render_into(writer),
+            Cell::Null => "NULL".render_into(writer),
+            Cell::Unreadable(error) => error.render_into(writer),
+        }
+    }
+}
+
+/// A window onto the rows of a result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Page {
+    pub index: u64,
+    pub size: u64,
+}
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            size: 100,
+        }
+    }
+}
+impl Page {
+    pub const SIZES: [u64; 4] = [10, 50, 100, 500];
+
+    pub fn count(&self, rows: u64) -> u64 {
+        ((rows + self.size - 1) / self.size).max(1)
+    }
+
+    /// Moves back onto the last page if `rows` doesn't reach this one
+    pub fn clamp(self, rows: u64) -> Self {
+        Self {
+            index: self.index.min(self.count(rows) - 1),
+            ..self
+        }
+    }
+
+    pub fn rows(&self, rows: u64) -> Range<u64> {
+        let start = (self.index * self.size).min(rows);
+
+        start..(start + self.size).min(rows)
+    }
+}
+
 #[component]
-pub fn Table<'a>(resolved: &'a ResolvedResult<'a>) {
-    let head = (0..resolved.resolved.column_count)
+pub fn Pager(page: Page, rows: u64) {
+    let count = page.count(rows);
+    let size = page.size;
+
+    let previous = on("page-previous", EventKind::Click, move |_| {
+        crate::goto_page(Page {
+            index: page.index.saturating_sub(1),
+            size,
+        })
+    });
+    let next = on("page-next", EventKind::Click, move |_| {
+        crate::goto_page(Page {
+            index: (page.index + 1).min(count - 1),
+            size,
+        })
+    });
+    let jump = on("page-jump", EventKind::Change, move |event| {
+        if let Ok(number) = event.payload.trim().parse::<u64>() {
+            crate::goto_page(Page {
+                index: number.saturating_sub(1),
+                size,
+            })
+        }
+    });
+    let resize = on("page-size", EventKind::Change, |event| {
+        if let Ok(size) = event.payload.parse() {
+            crate::goto_page(Page { index: 0, size })
+        }
+    });
+
+    let sizes = Page::SIZES
+        .iter()
+        .map(|option| {
+            let value = option.to_string();
+
+            if *option == page.size {
+                rsx! { <option value={value.clone()} selected={"selected"}>{value}</option> }
+            } else {
+                rsx! { <option value={value.clone()}>{value}</option> }
+            }
+        })
+        .contain();
+
+    rsx! {
+        <div class={"pager"}>
+            <button onclick={previous}>{"Previous"}</button>
+            {" Page "}
+            <input name={"page"} size={"4"} value={(page.index + 1).to_string()} onchange={jump}></input>
+            {" of "}{count.to_string()}{" "}
+            <button onclick={next}>{"Next"}</button>
+            {" "}
+            <select name={"page_size"} onchange={resize}>{sizes}</select>
+            {" rows per page"}
+        </div>
+    }
+}
+
+#[component]
+pub fn Table<'a>(data: &'a dyn ResultData, page: Page, view: &'a ResultView, rows: &'a [u64]) {
+    let page = page.clamp(rows.len() as u64);
+
+    let head = (0..data.column_count())
         .map(|col_idx| {
-            let column: &DuckDBColumn = resolved.column(col_idx);
-            let name = unsafe { CStr::from_ptr(column.name) }
-                .to_string_lossy()
-                .to_string();
+            let info = data.column_info(col_idx);
+            // results don't say whether a column is nullable, only which
+            // values are NULL
+            let nullable = if (0..data.row_count()).any(|row| data.is_null(col_idx, row)) {
+                " NULL"
+            } else {
+                ""
+            };
 
-            let type_: &str = resolved.consume(col_idx, 0).expect("consume").into();
+            let arrow = match view.sort {
+                Some(Sort { column, direction }) if column == col_idx => match direction {
+                    Direction::Ascending => " ▲",
+                    Direction::Descending => " ▼",
+                },
+                _ => "",
+            };
+            let sort = on(&format!("sort:{}", col_idx), EventKind::Click, move |_| {
+                crate::sort_by(col_idx)
+            });
+            let filter = on(
+                &format!("filter:{}", col_idx),
+                EventKind::Change,
+                move |event| crate::filter_by(col_idx, &event.payload),
+            );
+            let value = view.filters.get(&col_idx).cloned().unwrap_or_default();
 
-            rsx! { <td>{name}{": "}{type_}</td> }
+            rsx! {
+                <td>
+                    <div><a href={"#"} onclick={sort}>{info.name}{arrow}</a></div>
+                    <div><small><code>{info.sql_type}{nullable}</code></small></div>
+                    <div><input placeholder={"filter"} value={value} onchange={filter}></input></div>
+                </td>
+            }
         })
         .contain();
 
-    let body = (0..resolved.resolved.row_count)
-        .map(|row| {
+    let body = page
+        .rows(rows.len() as u64)
+        .map(|idx| {
+            let row = rows[idx as usize];
+
             rsx! {
                 <tr>
                     {
                         (
-                            (0..resolved.resolved.column_count)
+                            (0..data.column_count())
                             .map(|col| {
-                                let value = resolved.consume(col, row).expect("consume");
+                                let value = if data.is_null(col, row) {
+                                    Cell::Null
+                                } else {
+                                    // a value that can't be read says why, in its cell
+                                    data.value(col, row)
+                                        .map_or_else(|error| Cell::Unreadable(error.to_string()), Cell::Value)
+                                };
 
                                 rsx!{<td>{value}</td>}
                             })
@@ -72,31 +238,561 @@ pub fn Table<'a>(resolved: &'a ResolvedResult<'a>) {
         })
         .contain();
 
+    let pager = Pager {
+        page,
+        rows: rows.len() as u64,
+    };
+
+    rsx! {
+        <div>
+            <table>
+                <thead><tr>{head}</tr></thead>
+                <tbody>{body}</tbody>
+            </table>
+            {pager}
+        </div>
+    }
+}
+
+fn plan_node(node: PlanNode) -> String {
+    let children: String = node.children.into_iter().map(plan_node).collect();
+    let details = node
+        .details
+        .into_iter()
+        .map(|detail| rsx! { <li><code>{detail}</code></li> })
+        .contain();
+    let cardinality = node
+        .cardinality
+        .map(|cardinality| format!(" {} rows", cardinality));
+    let timing = node.timing.map(|timing| format!(" in {}", timing));
+
+    html! {
+        <li>
+            <details open={"true"}>
+                <summary><strong>{node.name}</strong>{cardinality}{timing}</summary>
+                <ul>{details}</ul>
+                <ul>{raw!(children.as_str())}</ul>
+            </details>
+        </li>
+    }
+}
+
+#[component]
+pub fn PlanView(plans: Vec<Plan>) {
+    let plans = plans
+        .into_iter()
+        .map(|plan| {
+            let header = plan
+                .header
+                .into_iter()
+                .map(|line| rsx! { <p>{line}</p> })
+                .contain();
+            let roots: String = plan.roots.into_iter().map(plan_node).collect();
+
+            rsx! {
+                <section>
+                    <h4>{plan.label}</h4>
+                    {header}
+                    <ul>{Html(roots)}</ul>
+                </section>
+            }
+        })
+        .contain();
+
+    rsx! { <div class={"plan"}>{plans}</div> }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn plural(count: i64, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+#[component]
+pub fn StatusBar(rows: u64, rows_affected: Option<i64>, execution: Duration, rendering: Duration) {
+    let rows = match rows_affected {
+        Some(affected) => format!("{} affected", plural(affected, "row")),
+        None => plural(rows as i64, "row"),
+    };
+
+    rsx! {
+        <p class={"status"}>
+            {rows}
+            {" in "}{format_duration(execution)}
+            {", rendered in "}{format_duration(rendering)}
+        </p>
+    }
+}
+
+/// Shown in place of the status line while the worker runs a query
+#[component]
+pub fn Running() {
+    let cancel = on("cancel", EventKind::Click, |_| crate::cancel_query());
+
+    rsx! {
+        <p class={"status"}>
+            {"Running… "}
+            <button onclick={cancel}>{"Cancel"}</button>
+        </p>
+    }
+}
+
+/// The failed query with the part DuckDB complained about marked
+#[component]
+pub fn ErrorView(error: QueryError) {
+    let heading = match error.kind {
+        ErrorKind::Unknown => "Error".to_string(),
+        ErrorKind::Interrupted => "Cancelled".to_string(),
+        ref kind => format!("{} Error", kind.name()),
+    };
+    let location = error
+        .position
+        .map(|position| format!(" at line {}, column {}", position.line, position.column + 1));
+    let context = error.span().map(|span| {
+        let query = &error.query;
+        // an empty span still needs something to mark
+        let marked = match &query[span.clone()] {
+            "" => " ".to_string(),
+            text => text.to_string(),
+        };
+
+        rsx! {
+            <pre><code>
+                {query[..span.start].to_string()}
+                <mark>{marked}</mark>
+                {query[span.end..].to_string()}
+            </code></pre>
+        }
+    });
+
+    rsx! {
+        <div class={"error"}>
+            <p><strong>{heading}</strong>{location}{": "}{error.message}</p>
+            {context}
+        </div>
+    }
+}
+
+/// A click handler that runs `query`, registered under `id`
+fn run_query_on_click(id: &str, query: String) -> String {
+    on(id, EventKind::Click, move |_| crate::run_query(&query))
+}
+
+/// A click handler that shows the files in `path`
+fn browse_on_click(path: PathBuf) -> String {
+    let id = format!("browse:{}", path.to_string_lossy());
+
+    on(&id, EventKind::Click, move |_| crate::browse(&path))
+}
+
+#[component]
+pub fn FileItem(entry: FileEntry) {
+    let path = entry.path.to_string_lossy().to_string();
+    let modified = entry.modified.map(format_time).unwrap_or_default();
+
+    let (label, onclick, size, kind) = match entry.kind {
+        EntryKind::Directory => (
+            format!("{}/", entry.name()),
+            browse_on_click(entry.path.clone()),
+            String::new(),
+            "directory",
+        ),
+        _ => (
+            entry.name(),
+            "event.preventDefault()".to_string(),
+            human_size(entry.size),
+            entry.format.map_or("file", DataFormat::name),
+        ),
+    };
+
+    // the worker has a filesystem of its own, without the page's files
+    let query = entry.format.filter(|_| !worker::enabled()).map(|format| {
+        let onclick = run_query_on_click(&format!("query-file:{}", path), format.query(&path));
+
+        rsx! { <button onclick={onclick}>{"Query"}</button> }
+    });
+
+    let name = entry.name();
+    let from = entry.path.clone();
+    let rename = on(&format!("rename:{}", path), EventKind::Click, move |_| {
+        if let Some(to) = dom::prompt("Rename to", &name) {
+            crate::rename_file(&from, &to);
+        }
+    });
+    let target = entry.path.clone();
+    let delete = on(&format!("delete:{}", path), EventKind::Click, move |_| {
+        if dom::confirm(&format!("Delete {}?", target.to_string_lossy())) {
+            crate::delete_file(&target);
+        }
+    });
+
+    rsx! {
+        <tr>
+            <td><a href={"#"} onclick={onclick}>{label}</a></td>
+            <td>{size}</td>
+            <td>{kind}</td>
+            <td>{modified}</td>
+            <td>
+                {query}
+                <button onclick={rename}>{"Rename"}</button>
+                <button onclick={delete}>{"Delete"}</button>
+            </td>
+        </tr>
+    }
+}
+
+#[component]
+pub fn FileRow(entry: Result<FileEntry, ListingError>) {
+    let (item, error) = match entry {
+        Ok(entry) => (Some(FileItem { entry }), None),
+        Err(error) => (None, Some(error.to_string())),
+    };
+    let error = error.map(|error| {
+        rsx! {
+            <tr><td colspan={"5"}><code>{error}</code></td></tr>
+        }
+    });
+
+    rsx! { <>{item}{error}</> }
+}
+
+#[component]
+pub fn FileBrowser(path: PathBuf) {
+    let parent = path.parent().map(|parent| {
+        let onclick = browse_on_click(parent.to_path_buf());
+
+        rsx! { <a href={"#"} onclick={onclick}>{".."}</a> }
+    });
+    let current = path.to_string_lossy().to_string();
+
+    let (listing, error) = match files::list(&path) {
+        Ok(entries) => (
+            Some(entries.into_iter().map(|entry| FileRow { entry }).contain()),
+            None,
+        ),
+        Err(error) => (None, Some(error.to_string())),
+    };
+    let listing = listing.map(|rows| {
+        rsx! {
+            <table>
+                <thead>
+                    <tr><td>{"Name"}</td><td>{"Size"}</td><td>{"Type"}</td><td>{"Modified"}</td><td></td></tr>
+                </thead>
+                <tbody>{rows}</tbody>
+            </table>
+        }
+    });
+    let error = error.map(|error| rsx! { <pre><code>{error}</code></pre> });
+    let hint = if worker::enabled() {
+        "Queries run in a worker, which can't see the files on this page."
+    } else {
+        ""
+    };
+
     rsx! {
-        <table>
-            <thead>{head}</thead>
-            <tbody>{body}</tbody>
-        </table>
+        <div>
+            <p>{current}{" "}{parent}</p>
+            <p class={"hint"}>{hint}</p>
+            {listing}
+            {error}
+        </div>
     }
 }
 
 #[component]
 pub fn Form() {
-    let files = Container(
-        std::fs::read_dir(std::path::Path::new("/"))
-            .expect("files")
-            .map(|f| rsx! { <li>{f.unwrap().path().to_str().unwrap().to_string()}</li> })
-            .collect(),
-    );
+    let query = routing::current().unwrap_or_default();
+    // trailing newline so the overlay is as tall as the textarea when the
+    // query ends in a blank line
+    let highlighted = Html(sql::highlight(&query) + "\n");
+    let submit = on("query", EventKind::Submit, |event| {
+        crate::run_query(&event.field("query").unwrap_or_default())
+    });
+    // the page can't handle a click on Cancel while it's running a query
+    let cancelling = if worker::requested() {
+        ""
+    } else {
+        "Queries run on this page and can't be cancelled. Add ?worker to the address to run them in a worker, where they can."
+    };
+    // re-highlights the overlay behind the editor, and refreshes any open
+    // completions
+    let input = on("editor-input", EventKind::Input, |event| {
+        dom::set_editor_highlight(&(sql::highlight(&event.payload) + "\n"));
+        complete::input();
+    });
+    // Ctrl+Enter runs the query, tab indents instead of moving focus, unless
+    // completions are open and want the key
+    let keydown = on_key("editor-keydown", |event| match event.payload.as_str() {
+        key if complete::key(key) => true,
+        "Ctrl+Enter" => {
+            dom::submit_editor();
+            true
+        }
+        "Tab" => {
+            dom::insert_in_editor("  ");
+            true
+        }
+        _ => false,
+    });
 
     rsx! {
         <div>
-            <form onsubmit={"event.preventDefault(); Module.ccall('callback', 'void', ['string'], [document.forms[0].query.value])"}>
-                <input placeholder={"select random()"} autofocus={"true"} name={"query"}></input>
+            <form onsubmit={submit}>
+                <div class={"editor"}>
+                    <pre aria-hidden={"true"}><code>{highlighted}</code></pre>
+                    <textarea
+                        placeholder={"select random()"}
+                        autofocus={"true"}
+                        spellcheck={"false"}
+                        name={"query"}
+                        rows={"6"}
+                        oninput={input}
+                        onscroll={"this.previousElementSibling.scrollTop = this.scrollTop; this.previousElementSibling.scrollLeft = this.scrollLeft"}
+                        onkeydown={keydown}
+                        onblur={"Module.autocomplete.close()"}
+                    >{query}</textarea>
+                </div>
+                <button type={"submit"}>{"Run"}</button>{" (Ctrl+Enter)"}
+                <p class={"hint"}>{cancelling}</p>
             </form>
-            <ul>
-                {files}
-            </ul>
+            <div
+                class={"dropzone"}
+                ondragover={"event.preventDefault()"}
+                ondrop={"event.preventDefault(); Module.uploadFiles(event.dataTransfer.files)"}
+            >
+                {"Drop CSV or Parquet files here, or "}
+                <input type={"file"} multiple={"true"} accept={".csv,.tsv,.parquet"} onchange={"Module.uploadFiles(this.files)"}></input>
+            </div>
         </div>
     }
 }
+
+/// Switches between the open databases, and lists what's attached to the
+/// current one
+#[component]
+pub fn DatabasePicker(
+    names: Vec<String>,
+    current: Option<String>,
+    attached: Vec<(String, String)>,
+) {
+    let options = names
+        .into_iter()
+        .map(|name| {
+            if current.as_ref() == Some(&name) {
+                rsx! { <option value={name.clone()} selected={"selected"}>{name}</option> }
+            } else {
+                rsx! { <option value={name.clone()}>{name}</option> }
+            }
+        })
+        .contain();
+    let select = on("database", EventKind::Change, |event| {
+        crate::select_database(&event.payload)
+    });
+    let open = on("database-open", EventKind::Click, |_| {
+        if let Some(path) = dom::prompt("File to open, or nothing for an in-memory database", "") {
+            crate::open_database_from(&path);
+        }
+    });
+    let attached = attached
+        .into_iter()
+        .map(|(name, file)| rsx! { <li><code>{name}</code>{" "}<small>{file}</small></li> })
+        .contain();
+
+    rsx! {
+        <div class={"databases"}>
+            <h4>{"Database"}</h4>
+            <select onchange={select}>{options}</select>
+            <button onclick={open}>{"Open…"}</button>
+            <ul>{attached}</ul>
+        </div>
+    }
+}
+
+#[component]
+pub fn Sidebar(catalog: Catalog) {
+    let schemas = catalog
+        .schemas
+        .into_iter()
+        .map(|Schema { name: schema, tables }| {
+            let tables = tables
+                .into_iter()
+                .map(|table| {
+                    let kind = match table.kind {
+                        TableKind::Table => "table",
+                        TableKind::View => "view",
+                    };
+                    let onclick = run_query_on_click(
+                        &format!("table:{}.{}", schema, table.name),
+                        format!(
+                            "select * from {}.{}",
+                            sql::quote_identifier(&schema),
+                            sql::quote_identifier(&table.name)
+                        ),
+                    );
+                    let columns = table
+                        .columns
+                        .into_iter()
+                        .map(|column| {
+                            let nullable = if column.nullable { "" } else { " not null" };
+
+                            rsx! { <li>{column.name}{": "}<code>{column.type_}{nullable}</code></li> }
+                        })
+                        .contain();
+
+                    rsx! {
+                        <li>
+                            <details>
+                                <summary><a href={"#"} onclick={onclick}>{table.name}</a>{" ("}{kind}{")"}</summary>
+                                <ul>{columns}</ul>
+                            </details>
+                        </li>
+                    }
+                })
+                .contain();
+
+            rsx! {
+                <details open={"true"}>
+                    <summary>{schema}</summary>
+                    <ul>{tables}</ul>
+                </details>
+            }
+        })
+        .contain();
+
+    rsx! {
+        <aside class={"sidebar"}>
+            <h4>{"Schema"}</h4>
+            {schemas}
+        </aside>
+    }
+}
+
+#[component]
+pub fn ChartView<'a>(data: &'a dyn ResultData, rows: &'a [u64], spec: Option<ChartSpec>) {
+    let columns: Vec<(String, String)> = (0..data.column_count())
+        .map(|col| (col.to_string(), data.column_info(col).name))
+        .collect();
+    let selected = |col: &str, chosen: bool| {
+        let (value, name) = columns
+            .iter()
+            .find(|(value, _)| value == col)
+            .cloned()
+            .unwrap_or_default();
+
+        if chosen {
+            rsx! { <option value={value} selected={"selected"}>{name}</option> }
+        } else {
+            rsx! { <option value={value}>{name}</option> }
+        }
+    };
+
+    let kinds = ChartKind::ALL
+        .iter()
+        .map(|kind| {
+            let chosen = spec.as_ref().map_or(false, |spec| spec.kind == *kind);
+
+            if chosen {
+                rsx! { <option value={kind.name()} selected={"selected"}>{kind.name()}</option> }
+            } else {
+                rsx! { <option value={kind.name()}>{kind.name()}</option> }
+            }
+        })
+        .contain();
+    let xs = columns
+        .iter()
+        .map(|(value, _)| {
+            let col: u64 = value.parse().unwrap_or_default();
+
+            selected(value, spec.as_ref().map_or(col == 0, |spec| spec.x == col))
+        })
+        .contain();
+    let ys = columns
+        .iter()
+        .map(|(value, _)| {
+            let col: u64 = value.parse().unwrap_or_default();
+
+            selected(
+                value,
+                spec.as_ref().map_or(false, |spec| spec.ys.contains(&col)),
+            )
+        })
+        .contain();
+
+    let svg = spec.map(|spec| match chart::render_svg(data, rows, &spec) {
+        Ok(svg) => Html(svg),
+        Err(error) => Html(html! { <pre><code>{error.to_string()}</code></pre> }),
+    });
+
+    let plot = on("chart", EventKind::Submit, |event| {
+        let spec = event
+            .field("kind")
+            .and_then(|kind| ChartKind::from_name(&kind))
+            .map(|kind| ChartSpec {
+                kind,
+                x: event
+                    .field("x")
+                    .and_then(|x| x.parse().ok())
+                    .unwrap_or_default(),
+                ys: event
+                    .values("ys")
+                    .iter()
+                    .filter_map(|y| y.parse().ok())
+                    .collect(),
+            });
+
+        crate::chart(spec)
+    });
+
+    rsx! {
+        <div class={"chart"}>
+            <form name={"chart"} onsubmit={plot}>
+                <select name={"kind"}>{kinds}</select>
+                {" of "}
+                <select name={"ys"} multiple={"multiple"}>{ys}</select>
+                {" against "}
+                <select name={"x"}>{xs}</select>
+                {" "}
+                <button>{"Plot"}</button>
+            </form>
+            {svg}
+        </div>
+    }
+}
+
+#[component]
+pub fn HistoryPanel(entries: Vec<HistoryEntry>) {
+    let items = entries
+        .into_iter()
+        .enumerate()
+        .rev()
+        .map(|(idx, entry)| {
+            let query = entry.query.clone();
+            let restore = on(&format!("history-restore:{}", idx), EventKind::Click, move |_| {
+                dom::set_editor_query(&query);
+            });
+            let rerun = run_query_on_click(&format!("history-run:{}", idx), entry.query.clone());
+            let state = if entry.success { "✓" } else { "✗" };
+
+            rsx! {
+                <li>
+                    {state}{" "}
+                    <a href={"#"} onclick={restore}><code>{entry.query}</code></a>
+                    {" "}
+                    <small>{format_time(entry.timestamp)}{", "}{format_duration(entry.duration)}</small>
+                    {" "}
+                    <button onclick={rerun}>{"Run"}</button>
+                </li>
+            }
+        })
+        .contain();
+
+    rsx! {
+        <details class={"history"}>
+            <summary>{"History"}</summary>
+            <button onclick={on("history-clear", EventKind::Click, |_| crate::clear_history())}>{"Clear"}</button>
+            <ol>{items}</ol>
+        </details>
+    }
+}
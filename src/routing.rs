@@ -0,0 +1,68 @@
+use crate::jse;
+use std::cell::RefCell;
+use std::thread_local;
+
+thread_local! {
+    static CURRENT: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Calls `hash_changed` whenever the user navigates with back/forward, or
+/// opens a link to a different query
+pub fn install() -> i32 {
+    jse!(
+        b"
+        if (typeof window !== 'undefined') {
+            window.addEventListener('hashchange', function () {
+                Module.ccall('hash_changed', 'void', [], []);
+            });
+        }
+    \x00"
+    )
+}
+
+/// The query encoded in `location.hash`, if there is one. A hash that isn't
+/// valid percent-encoding, as when it was typed by hand, is taken as is
+pub fn read_hash() -> Option<String> {
+    jse!(
+        -> Option<String>,
+        b"
+        if (typeof location === 'undefined' || location.hash.length < 2) { return 0; }
+        var hash = location.hash.slice(1);
+        try {
+            hash = decodeURIComponent(hash);
+        } catch (e) {}
+        return allocateUTF8(hash);
+    \x00"
+    )
+}
+
+/// Records `query` as the current page, adding a browser history entry if
+/// it differs from the last one
+pub fn set_hash(query: &str) -> i32 {
+    CURRENT.with(|current| current.replace(Some(query.to_string())));
+
+    jse!(
+        b"
+        if (typeof location !== 'undefined') {
+            var hash = '#' + encodeURIComponent(UTF8ToString($0, $1));
+            if (location.hash !== hash) { location.hash = hash; }
+        }
+    \x00",
+        query
+    )
+}
+
+/// The query being shown, if any
+pub fn current() -> Option<String> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// The query from the hash, unless it's the one already being shown
+pub fn navigated_to() -> Option<String> {
+    let query = read_hash()?;
+
+    CURRENT.with(|current| match &*current.borrow() {
+        Some(current) if *current == query => None,
+        _ => Some(query),
+    })
+}
@@ -0,0 +1,341 @@
+/// Each non-empty statement in `tokens`, without whitespace and comments.
+/// Semicolons inside strings are part of the string token, so they don't
+/// end a statement
+fn statements<'a>(tokens: &'a [Token<'a>]) -> impl Iterator<Item = Vec<&'a Token<'a>>> {
+    tokens
+        .split(|token| token.kind == TokenKind::Punctuation && token.text == ";")
+        .map(|statement| {
+            statement
+                .iter()
+                .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+                .collect::<Vec<_>>()
+        })
+        .filter(|statement| !statement.is_empty())
+}
+
+/// The leading keyword of each statement in `query`, lowercased
+pub fn statement_keywords(query: &str) -> Vec<String> {
+    statements(&tokenize(query))
+        .map(|statement| statement[0].text.to_lowercase())
+        .collect()
+}
+
+/// The keyword saying what `statement` does, lowercased. A leading `with`
+/// is looked past, to the statement its common table expressions are for
+fn verb(statement: &[&Token]) -> Option<String> {
+    let mut depth = 0;
+    let mut keywords = statement.iter().filter_map(|token| {
+        match token.text {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 && token.kind == TokenKind::Keyword {
+            Some(token.text.to_lowercase())
+        } else {
+            None
+        }
+    });
+
+    match statement.first()?.text.to_lowercase().as_str() {
+        "with" => keywords
+            .find(|keyword| !matches!(keyword.as_str(), "with" | "recursive" | "as" | "not")),
+        first => Some(first.to_string()),
+    }
+}
+
+/// Whether the last statement in `query` modifies rows, so its result is a
+/// count of affected rows rather than data
+pub fn is_dml(query: &str) -> bool {
+    let tokens = tokenize(query);
+
+    matches!(
+        statements(&tokens)
+            .last()
+            .and_then(|statement| verb(&statement))
+            .as_deref(),
+        Some("insert") | Some("update") | Some("delete") | Some("copy")
+    )
+}
+
+/// Whether every statement in `query` only reads the database, so it's
+/// safe to run without asking, as when it comes from a link
+pub fn is_read_only(query: &str) -> bool {
+    let tokens = tokenize(query);
+    let mut statements = statements(&tokens);
+
+    statements.all(|statement| match verb(&statement).as_deref() {
+        Some("select") | Some("values") | Some("describe") | Some("show") => true,
+        // explain analyze runs the statement it explains
+        Some("explain") => !statement
+            .iter()
+            .any(|token| token.text.eq_ignore_ascii_case("analyze")),
+        _ => false,
+    })
+}
+
+pub const KEYWORDS: &[&str] = &[
+    "all",
+    "alter",
+    "analyze",
+    "and",
+    "any",
+    "as",
+    "asc",
+    "attach",
+    "begin",
+    "between",
+    "by",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "column",
+    "commit",
+    "constraint",
+    "copy",
+    "create",
+    "cross",
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "default",
+    "delete",
+    "desc",
+    "describe",
+    "detach",
+    "distinct",
+    "drop",
+    "else",
+    "end",
+    "except",
+    "exists",
+    "explain",
+    "export",
+    "false",
+    "filter",
+    "first",
+    "following",
+    "for",
+    "foreign",
+    "from",
+    "full",
+    "function",
+    "group",
+    "having",
+    "ilike",
+    "import",
+    "in",
+    "index",
+    "inner",
+    "insert",
+    "intersect",
+    "interval",
+    "into",
+    "is",
+    "join",
+    "key",
+    "last",
+    "left",
+    "like",
+    "limit",
+    "macro",
+    "natural",
+    "not",
+    "null",
+    "nulls",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "outer",
+    "over",
+    "partition",
+    "pragma",
+    "preceding",
+    "primary",
+    "range",
+    "recursive",
+    "references",
+    "rename",
+    "replace",
+    "right",
+    "rollback",
+    "row",
+    "rows",
+    "schema",
+    "select",
+    "sequence",
+    "set",
+    "show",
+    "similar",
+    "table",
+    "temp",
+    "temporary",
+    "then",
+    "to",
+    "transaction",
+    "true",
+    "unbounded",
+    "union",
+    "unique",
+    "update",
+    "using",
+    "vacuum",
+    "values",
+    "view",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Keyword,
+    Identifier,
+    QuotedIdentifier,
+    String,
+    Number,
+    Comment,
+    Operator,
+    Punctuation,
+    Whitespace,
+}
+impl TokenKind {
+    fn class(self) -> Option<&'static str> {
+        match self {
+            TokenKind::Keyword => Some("sql-keyword"),
+            TokenKind::QuotedIdentifier => Some("sql-identifier"),
+            TokenKind::String => Some("sql-string"),
+            TokenKind::Number => Some("sql-number"),
+            TokenKind::Comment => Some("sql-comment"),
+            TokenKind::Operator => Some("sql-operator"),
+            TokenKind::Identifier | TokenKind::Punctuation | TokenKind::Whitespace => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+}
+
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS.contains(&word.to_lowercase().as_str())
+}
+
+/// `name` as a quoted identifier, with embedded quotes doubled
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Splits `sql` into tokens covering every character, so unterminated
+/// strings and comments run to the end of the input
+pub fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<(usize, char)> = sql.char_indices().collect();
+    let offset = |idx: usize| chars.get(idx).map_or(sql.len(), |(offset, _)| *offset);
+    let peek = |idx: usize| chars.get(idx).map(|(_, c)| *c);
+
+    let mut tokens = vec![];
+    let mut idx = 0;
+    while let Some(c) = peek(idx) {
+        let start = idx;
+        let kind = if c.is_whitespace() {
+            while peek(idx).map_or(false, char::is_whitespace) {
+                idx += 1;
+            }
+            TokenKind::Whitespace
+        } else if c == '-' && peek(idx + 1) == Some('-') {
+            while peek(idx).map_or(false, |c| c != '\n') {
+                idx += 1;
+            }
+            TokenKind::Comment
+        } else if c == '/' && peek(idx + 1) == Some('*') {
+            idx += 2;
+            while peek(idx).is_some() && !(peek(idx) == Some('*') && peek(idx + 1) == Some('/')) {
+                idx += 1;
+            }
+            idx = (idx + 2).min(chars.len());
+            TokenKind::Comment
+        } else if c == '\'' || c == '"' {
+            idx += 1;
+            loop {
+                match peek(idx) {
+                    Some(q) if q == c => {
+                        idx += 1;
+                        // a doubled quote is an escaped quote
+                        if peek(idx) != Some(c) {
+                            break;
+                        }
+                        idx += 1;
+                    }
+                    Some(_) => idx += 1,
+                    None => break,
+                }
+            }
+            if c == '\'' {
+                TokenKind::String
+            } else {
+                TokenKind::QuotedIdentifier
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && peek(idx + 1).map_or(false, |n| n.is_ascii_digit()))
+        {
+            while peek(idx).map_or(false, |c| c.is_ascii_digit() || c == '.') {
+                idx += 1;
+            }
+            if matches!(peek(idx), Some('e') | Some('E'))
+                && peek(idx + 1).map_or(false, |n| n.is_ascii_digit() || n == '-' || n == '+')
+            {
+                idx += 2;
+                while peek(idx).map_or(false, |c| c.is_ascii_digit()) {
+                    idx += 1;
+                }
+            }
+            TokenKind::Number
+        } else if c.is_alphabetic() || c == '_' {
+            while peek(idx).map_or(false, |c| c.is_alphanumeric() || c == '_' || c == '$') {
+                idx += 1;
+            }
+            if is_keyword(&sql[offset(start)..offset(idx)]) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Identifier
+            }
+        } else if "<>=!|+-*/%:^~&".contains(c) {
+            while peek(idx).map_or(false, |c| "<>=!|+-*/%:^~&".contains(c)) {
+                idx += 1;
+            }
+            TokenKind::Operator
+        } else {
+            idx += 1;
+            TokenKind::Punctuation
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &sql[offset(start)..offset(idx)],
+        });
+    }
+
+    tokens
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// `sql` as html, with tokens wrapped in spans classed by their kind
+pub fn highlight(sql: &str) -> String {
+    tokenize(sql)
+        .into_iter()
+        .map(|token| match token.kind.class() {
+            Some(class) => format!(r#"<span class="{}">{}</span>"#, class, escape(token.text)),
+            None => escape(token.text),
+        })
+        .collect()
+}
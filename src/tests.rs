@@ -1,12 +1,14 @@
 use crate::db::DB;
+#[cfg(target_os = "emscripten")]
 use crate::jse;
-use crate::{
-    c_char, callback, duckdb_date, duckdb_time, duckdb_timestamp, emscripten_asm_const_int, hook,
-    main,
-};
+#[cfg(target_os = "emscripten")]
+use crate::{callback, hook, main};
+use rust_wasm_duckdb::types::{duckdb_date, duckdb_time, duckdb_timestamp};
 use speculate::speculate;
-use std::ffi::{CStr, CString};
+#[cfg(target_os = "emscripten")]
+use std::ffi::CString;
 
+#[cfg(target_os = "emscripten")]
 fn parse(html: String) -> kuchiki::NodeRef {
     use kuchiki::traits::TendrilSink;
 
@@ -20,22 +22,59 @@ fn parse(html: String) -> kuchiki::NodeRef {
     resultant.first_child().expect("first_child")
 }
 
+#[cfg(target_os = "emscripten")]
 fn get_document_html() -> String {
-    let ptr = jse!(b"return allocateUTF8OnStack(document.body.innerHTML);\x00");
+    jse!(-> String, b"return allocateUTF8(document.body.innerHTML);\x00")
+}
+
+/// Backs `document.getElementById` with plain objects, so regions can be
+/// patched without a DOM, until dropped
+#[cfg(target_os = "emscripten")]
+struct FakeRegions;
+#[cfg(target_os = "emscripten")]
+impl FakeRegions {
+    fn install() -> Self {
+        jse!(
+            b"
+            global.realGetElementById = global.document.getElementById;
+            var regions = {};
+            global.document.getElementById = function (id) {
+                return regions[id] = regions[id] || {innerHTML: ''};
+            };
+        \x00"
+        );
 
-    return unsafe { CStr::from_ptr(ptr as *const c_char) }
-        .to_string_lossy()
-        .to_string();
+        FakeRegions
+    }
+}
+#[cfg(target_os = "emscripten")]
+impl Drop for FakeRegions {
+    fn drop(&mut self) {
+        jse!(
+            b"
+            if (global.realGetElementById) {
+                global.document.getElementById = global.realGetElementById;
+            } else {
+                delete global.document.getElementById;
+            }
+            delete global.realGetElementById;
+        \x00"
+        );
+    }
 }
 
 speculate! {
     before {
-        std::panic::set_hook(Box::new(hook));
+        #[cfg(target_os = "emscripten")]
+        {
+            std::panic::set_hook(Box::new(hook));
 
-        jse!(b"global.document = {body: {}};\x00");
+            jse!(b"global.document = {body: {}};\x00");
+        }
     }
 
     after {
+        #[cfg(target_os = "emscripten")]
         jse!(b"delete global.document;\x00");
     }
 
@@ -46,12 +85,14 @@ speculate! {
         );
     }
 
+    #[cfg(target_os = "emscripten")]
     fn basic_test(query: &str) {
         main().unwrap();
         let string = CString::new(query).unwrap();
         callback(string.as_ptr());
     }
 
+    #[cfg(target_os = "emscripten")]
     test "version check" {
         basic_test("pragma version");
     }
@@ -62,6 +103,7 @@ speculate! {
     }
     */
 
+    #[cfg(target_os = "emscripten")]
     test "works" {
         basic_test("select 1");
 
@@ -89,7 +131,7 @@ speculate! {
 
             let data = result.consume(0, 0)?;
 
-            assert_eq!(matches!(data, Date(date)), true);
+            assert_eq!(matches!(data, Date(_)), true);
 
             Ok(())
         }
@@ -103,6 +145,7 @@ speculate! {
         assert_eq!(value.to_string(), "1996-08-07T12:10:00.0");
     }
 
+    #[cfg(target_os = "emscripten")]
     test "multi args works" {
         fn addition(a: i32, b: i32) -> i32 {
             jse!(b"return $0 + $1;\x00", a, b)
@@ -137,4 +180,899 @@ speculate! {
 
         conn.query("select 1").expect("query");
     }
+
+    #[cfg(target_os = "emscripten")]
+    test "data format detection" {
+        use crate::files::DataFormat;
+        use std::path::Path;
+
+        let format = DataFormat::detect(Path::new("/trips.CSV")).expect("csv");
+        assert_eq!(format, DataFormat::Csv);
+        assert_eq!(format.query("/it's.csv"), "select * from read_csv_auto('/it''s.csv')");
+
+        assert_eq!(DataFormat::detect(Path::new("/a.parquet")), Some(DataFormat::Parquet));
+        // duckdb has no json reader to query these with
+        assert_eq!(DataFormat::detect(Path::new("/a.json")), None);
+        assert_eq!(DataFormat::detect(Path::new("/tmp")), None);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "file times" {
+        use crate::files::format_time;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let time = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3_723);
+
+        assert_eq!(format_time(time), "2000-02-29 01:02:03");
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "listing tolerates bad directories" {
+        use crate::files::list;
+        use std::path::Path;
+
+        assert_eq!(list(Path::new("/does/not/exist")).is_err(), true);
+        assert_eq!(list(Path::new("/")).expect("root").is_empty(), false);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "catalog" {
+        use crate::catalog::{changes_catalog, Catalog, TableKind};
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+
+        conn.query("create table people (name varchar not null, age integer)").expect("create");
+        conn.query("create view adults as select * from people where age >= 18").expect("view");
+
+        let catalog = Catalog::load(&conn).expect("catalog");
+        let tables = &catalog.schemas.iter().find(|s| s.name == "main").expect("main").tables;
+
+        let people = tables.iter().find(|t| t.name == "people").expect("people");
+        assert_eq!(people.kind, TableKind::Table);
+        assert_eq!(people.columns.len(), 2);
+        assert_eq!(people.columns[0].nullable, false);
+
+        let adults = tables.iter().find(|t| t.name == "adults").expect("adults");
+        assert_eq!(adults.kind, TableKind::View);
+
+        assert_eq!(changes_catalog("-- comment\n  CREATE table a (b int)"), true);
+        assert_eq!(changes_catalog("select 1"), false);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "explain" {
+        use crate::explain::{from_result, is_explain, parse};
+
+        let plan = parse(
+            "physical_plan".to_string(),
+            "┌───────────┐\n│ HASH_JOIN │\n│   ─ ─ ─   ├────┐\n│    a=b    │    │\n└─────┬─────┘    │\n┌─────┴─────┐┌─────┴─────┐\n│ SEQ_SCAN  ││ SEQ_SCAN  │\n│   ─ ─ ─   ││   ─ ─ ─   │\n│    10     ││  (0.01s)  │\n└───────────┘└───────────┘",
+        );
+        assert_eq!(plan.roots.len(), 1);
+
+        let join = &plan.roots[0];
+        assert_eq!(join.name, "HASH_JOIN");
+        assert_eq!(join.details, vec!["a=b".to_string()]);
+        assert_eq!(join.children.len(), 2);
+        assert_eq!(join.children[0].cardinality, Some(10));
+        assert_eq!(join.children[1].timing, Some("0.01s".to_string()));
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let resolved = conn.query("explain select 1").expect("explain");
+
+        assert_eq!(is_explain(&resolved), true);
+        assert_eq!(from_result(&resolved).expect("plans").is_empty(), false);
+    }
+
+    test "rows affected" {
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+
+        conn.query("create table numbers (n integer)").expect("create");
+
+        let inserted = conn.query("insert into numbers select * from range(5)").expect("insert");
+        assert_eq!(inserted.rows_affected(), Some(5));
+
+        let selected = conn.query("select * from numbers").expect("select");
+        assert_eq!(selected.rows_affected(), None);
+        assert_eq!(selected.row_count(), 5);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "pagination" {
+        use crate::rendering::Page;
+
+        let page = Page { index: 7, size: 10 };
+
+        assert_eq!(page.count(95), 10);
+        assert_eq!(page.rows(95), 70..80);
+        assert_eq!(page.clamp(25), Page { index: 2, size: 10 });
+        assert_eq!(page.clamp(25).rows(25), 20..25);
+        assert_eq!(page.clamp(0).rows(0), 0..0);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "sorting and filtering" {
+        use crate::view::{compare, matches, ResultView};
+        use crate::DbType;
+        use rust_wasm_duckdb::types::duckdb_blob;
+        use std::cmp::Ordering;
+
+        assert_eq!(compare(&DbType::Integer(2), &DbType::Integer(10)), Ordering::Less);
+        assert_eq!(
+            compare(&DbType::Date(duckdb_date::new(2021, 1, 2)), &DbType::Date(duckdb_date::new(2020, 12, 31))),
+            Ordering::Greater
+        );
+        assert_eq!(compare(&DbType::Double(f64::NAN), &DbType::Double(1.0)), Ordering::Greater);
+        assert_eq!(
+            compare(&DbType::Blob(duckdb_blob::new(b"b")), &DbType::Blob(duckdb_blob::new(b"ab"))),
+            Ordering::Greater
+        );
+
+        assert_eq!(matches(">= 10", &DbType::Bigint(10)), true);
+        assert_eq!(matches("!=10", &DbType::Bigint(10)), false);
+        assert_eq!(matches("DUCK", &DbType::String("duckdb".to_string())), true);
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let resolved = conn
+            .query("select * from (values (3, 'c'), (1, 'a'), (2, 'b'), (10, 'aa')) t(n, s)")
+            .expect("query");
+
+        let mut view = ResultView::default();
+        view.toggle_sort(0);
+        assert_eq!(*view.rows(&resolved).expect("rows"), vec![1, 2, 0, 3]);
+        // the order is kept until the sort or filters change
+        assert_eq!(*view.rows(&resolved).expect("rows"), vec![1, 2, 0, 3]);
+
+        view.toggle_sort(0);
+        view.set_filter(1, "a");
+        assert_eq!(*view.rows(&resolved).expect("rows"), vec![3, 1]);
+
+        // NULLs sort last either way, and only a filter for NULL finds them
+        let nulls = conn
+            .query("select * from (values (2), (null), (1)) t(n)")
+            .expect("query");
+        let mut view = ResultView::default();
+        view.toggle_sort(0);
+        assert_eq!(*view.rows(&nulls).expect("rows"), vec![2, 0, 1]);
+        view.toggle_sort(0);
+        assert_eq!(*view.rows(&nulls).expect("rows"), vec![0, 2, 1]);
+        view.toggle_sort(0);
+        view.set_filter(0, "<= 1");
+        assert_eq!(*view.rows(&nulls).expect("rows"), vec![2]);
+        view.set_filter(0, "null");
+        assert_eq!(*view.rows(&nulls).expect("rows"), vec![1]);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "table cells" {
+        use crate::data::ResultData;
+        use crate::rendering::{Page, Table};
+        use crate::view::ResultView;
+        use crate::DbType;
+        use render::html;
+        use rust_wasm_duckdb::ColumnInfo;
+        use std::error::Error;
+        use std::time::Duration;
+
+        #[derive(Debug)]
+        struct Unreadable;
+        impl ResultData for Unreadable {
+            fn column_count(&self) -> u64 {
+                1
+            }
+            fn column_info(&self, _col: u64) -> ColumnInfo {
+                ColumnInfo {
+                    name: "x".to_string(),
+                    type_: rust_wasm_duckdb::types::duckdb_type::DUCKDB_TYPE_INTEGER,
+                    sql_type: "INTEGER".to_string(),
+                }
+            }
+            fn row_count(&self) -> u64 {
+                1
+            }
+            fn value(&self, _col: u64, _row: u64) -> Result<DbType, Box<dyn Error>> {
+                Err("unreadable".into())
+            }
+            fn is_null(&self, _col: u64, _row: u64) -> bool {
+                false
+            }
+            fn execution_time(&self) -> Duration {
+                Duration::default()
+            }
+            fn rows_affected(&self) -> Option<i64> {
+                None
+            }
+        }
+
+        let view = ResultView::default();
+        let table = html! {
+            <>{Table { data: &Unreadable, page: Page::default(), view: &view, rows: &[0] }}</>
+        };
+        assert!(table.contains("<td>unreadable</td>"));
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let nulls = conn.query("select null::integer as n").expect("query");
+        let table = html! {
+            <>{Table { data: &nulls, page: Page::default(), view: &view, rows: &[0] }}</>
+        };
+        assert!(table.contains("<td>NULL</td>"));
+        // headers are a row of their own, and say NULL only when they've seen one
+        assert!(table.contains("<thead><tr><td>"));
+        assert!(table.contains("<code>INTEGER NULL</code>"));
+        let none = conn.query("select null::integer as n where false").expect("query");
+        let table = html! {
+            <>{Table { data: &none, page: Page::default(), view: &view, rows: &[] }}</>
+        };
+        assert!(table.contains("<code>INTEGER</code>"));
+    }
+
+    test "column info without rows" {
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+
+        let resolved = conn
+            .query("select 1.5::decimal(18,3) as price, null::varchar as note where false")
+            .expect("query");
+        assert_eq!(resolved.row_count(), 0);
+
+        let price = resolved.column_info(0);
+        assert_eq!(price.name, "price");
+        assert_eq!(price.sql_type, "DECIMAL(18,3)");
+        assert_eq!(resolved.column_info(1).sql_type, "VARCHAR");
+
+        // the C api has no DECIMAL, so the values come back as doubles
+        let resolved = conn.query("select 1.5::decimal(18,3) as price").expect("query");
+        assert_eq!(resolved.column_info(0).sql_type, "DECIMAL(18,3)");
+        assert_eq!(resolved.column_info(0).type_, rust_wasm_duckdb::types::duckdb_type::DUCKDB_TYPE_DOUBLE);
+        assert_eq!(resolved.consume(0, 0).expect("value").to_string(), "1.5");
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "charts" {
+        use crate::chart::{render_svg, ChartKind, ChartSpec};
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let resolved = conn
+            .query("select * from (values ('a', 1, 2.5), ('b', 3, 1.0), ('c', 2, 4.0)) t(name, x, y)")
+            .expect("query");
+
+        let bars = render_svg(&resolved, &[0, 1, 2], &ChartSpec { kind: ChartKind::Bar, x: 0, ys: vec![1, 2] })
+            .expect("bar");
+        assert_eq!(bars.starts_with("<svg"), true);
+        assert_eq!(bars.matches("<rect").count(), 6);
+        assert_eq!(bars.contains(">name</text>"), true);
+
+        let scatter = render_svg(&resolved, &[0, 1, 2], &ChartSpec { kind: ChartKind::Scatter, x: 1, ys: vec![2] })
+            .expect("scatter");
+        assert_eq!(scatter.matches("<circle").count(), 3);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "history roundtrip" {
+        use crate::history::{decode, encode, HistoryEntry};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let entries = vec![
+            HistoryEntry {
+                query: "select 'tab\there',\n  2 -- \\ comment".to_string(),
+                timestamp: UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+                duration: Duration::from_micros(1_500),
+                success: true,
+            },
+            HistoryEntry {
+                query: "selec 1".to_string(),
+                timestamp: UNIX_EPOCH + Duration::from_secs(1_600_000_060),
+                duration: Duration::from_micros(20),
+                success: false,
+            },
+        ];
+
+        assert_eq!(decode(&encode(&entries)), entries);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "url hash" {
+        use crate::routing::{navigated_to, read_hash, set_hash};
+
+        jse!(b"global.savedLocation = global.location; global.location = {hash: ''};\x00");
+
+        set_hash("select 'a b'");
+        assert_eq!(read_hash(), Some("select 'a b'".to_string()));
+        assert_eq!(navigated_to(), None);
+
+        jse!(b"global.location.hash = '#select%202';\x00");
+        assert_eq!(navigated_to(), Some("select 2".to_string()));
+
+        jse!(b"global.location.hash = '#%E0%A4%A';\x00");
+        assert_eq!(read_hash(), Some("%E0%A4%A".to_string()));
+
+        jse!(b"global.location = global.savedLocation; delete global.savedLocation;\x00");
+    }
+
+    test "sql highlighting" {
+        use crate::sql::{highlight, quote_identifier, tokenize, TokenKind};
+
+        let query = "select 'it''s', \"Name\" from t -- done\nwhere n >= 1.5";
+        let tokens = tokenize(query);
+
+        assert_eq!(tokens.iter().map(|t| t.text).collect::<String>(), query);
+        let kinds: Vec<(TokenKind, &str)> = tokens
+            .iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, t.text))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Keyword, "select"),
+                (TokenKind::String, "'it''s'"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::QuotedIdentifier, "\"Name\""),
+                (TokenKind::Keyword, "from"),
+                (TokenKind::Identifier, "t"),
+                (TokenKind::Comment, "-- done"),
+                (TokenKind::Keyword, "where"),
+                (TokenKind::Identifier, "n"),
+                (TokenKind::Operator, ">="),
+                (TokenKind::Number, "1.5"),
+            ]
+        );
+
+        assert_eq!(tokenize("'unterminated").len(), 1);
+
+        let quoted = quote_identifier("say \"hi\"");
+        assert_eq!(quoted, "\"say \"\"hi\"\"\"");
+        assert_eq!(tokenize(&quoted).len(), 1);
+        assert_eq!(
+            highlight("SELECT '<b>'"),
+            "<span class=\"sql-keyword\">SELECT</span> <span class=\"sql-string\">'&lt;b&gt;'</span>"
+        );
+    }
+
+    test "statement keywords" {
+        use crate::sql::{is_dml, is_read_only, statement_keywords};
+
+        assert_eq!(statement_keywords("-- comment\n  CREATE table a (b int); select 1;"), vec!["create", "select"]);
+        assert_eq!(statement_keywords("/* note */ create table t(i int)"), vec!["create"]);
+        assert_eq!(statement_keywords("select ';drop'"), vec!["select"]);
+
+        assert!(is_dml("/* note */ insert into t values (1)"));
+        assert!(is_dml("with x as (select 1 as i), y(j) as (select 2) insert into t select i from x"));
+        assert!(!is_dml("with x as (insert into t values (1)) select * from x"));
+        assert!(!is_dml("insert into t values (1); select 'delete'"));
+
+        assert!(is_read_only("select 1; with x as (select 1) select * from x"));
+        assert!(is_read_only("explain select 1"));
+        assert!(!is_read_only("select 1; drop table t"));
+        assert!(!is_read_only("with x as (select 1) delete from t"));
+        assert!(!is_read_only("explain analyze insert into t values (1)"));
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "autocomplete" {
+        use crate::catalog::Catalog;
+        use crate::complete::{complete, CompletionKind};
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        conn.query("create table trips (fare double, \"Pickup Time\" timestamp)").expect("create");
+        let catalog = Catalog::load(&conn).expect("catalog");
+
+        let (prefix, completions) = complete(&catalog, "select * from tr", "");
+        assert_eq!(prefix, "tr");
+        assert_eq!(completions[0].label, "trips");
+        assert_eq!(completions[0].kind, CompletionKind::Table);
+
+        let (prefix, completions) = complete(&catalog, "select t.", " from trips t");
+        assert_eq!(prefix, "");
+        let labels: Vec<&str> = completions.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, vec!["fare", "\"Pickup Time\""]);
+
+        let (_, completions) = complete(&catalog, "sel", "");
+        assert!(completions.iter().any(|c| c.label == "select" && c.kind == CompletionKind::Keyword));
+
+        assert!(!catalog.functions.is_empty());
+    }
+
+    test "query errors" {
+        use crate::error::{ErrorKind, Position, QueryError};
+
+        let query = "select 1,\n  2 frm t";
+        let error = QueryError::parse(
+            query,
+            "Parser Error: syntax error at or near \"t\"\nLINE 2:   2 frm t\n                ^",
+        );
+        assert_eq!(error.kind, ErrorKind::Parser);
+        assert_eq!(error.message, "syntax error at or near \"t\"");
+        assert_eq!(error.position, Some(Position { line: 2, column: 8 }));
+        assert_eq!(error.span().map(|span| &query[span]), Some("t"));
+
+        let error = QueryError::parse("select x", "Binder Error: Referenced column \"x\" not found");
+        assert_eq!(error.kind, ErrorKind::Binder);
+        assert_eq!(error.position, None);
+        assert_eq!(error.span(), None);
+
+        // messages of our own aren't parsed for a kind
+        let error = QueryError::new("select 1", ErrorKind::Unknown, "Syntax Error: not really");
+        assert_eq!(error.kind, ErrorKind::Unknown);
+        assert_eq!(error.to_string(), "Syntax Error: not really");
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let error = conn.query("selec 1").expect_err("should fail");
+        assert_eq!(error.kind, ErrorKind::Parser);
+        assert_eq!(error.query, "selec 1");
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "patching regions" {
+        use crate::dom::{mount, patch, Region};
+
+        mount();
+        assert!(get_document_html().contains("<div id=\"results\"></div>"));
+
+        let _regions = FakeRegions::install();
+
+        patch(Region::Status, "<p>1 row</p>".to_string());
+        patch(Region::Results, "<table></table>".to_string());
+
+        let rendered = jse!(
+            -> String,
+            b"return allocateUTF8(document.getElementById('status').innerHTML + '|' + document.getElementById('results').innerHTML);\x00"
+        );
+        assert_eq!(rendered, "<p>1 row</p>|<table></table>");
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "typed jse arguments" {
+        let text = "naïve — 日本";
+        let echoed = jse!(-> String, b"return allocateUTF8(UTF8ToString($0, $1));\x00", text);
+        assert_eq!(echoed, text);
+
+        // strings aren't nul terminated, so only their length is read
+        let echoed = jse!(-> String, b"return allocateUTF8(UTF8ToString($0, $1));\x00", &text[..6]);
+        assert_eq!(echoed, "naïve");
+
+        let sum = jse!(-> f64, b"return $0 + $1 + $2;\x00", 1i32, 0.25f64, 1.5f64);
+        assert_eq!(sum, 2.75);
+
+        let bytes: &[u8] = &[1, 2, 250];
+        let total = jse!(
+            b"return HEAPU8.subarray($0, $0 + $1).reduce(function (a, b) { return a + b; }, 0);\x00",
+            bytes
+        );
+        assert_eq!(total, 253);
+
+        assert_eq!(jse!(-> bool, b"return $0 ? 0 : 1;\x00", true), false);
+        assert_eq!(jse!(-> Option<String>, b"return 0;\x00"), None);
+        jse!(-> (), b"global.typedJse = UTF8ToString($0, $1);\x00", "unit");
+        assert_eq!(jse!(-> String, b"return allocateUTF8(global.typedJse);\x00"), "unit");
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "long and multibyte html" {
+        use crate::{set_body_html, set_page_title};
+
+        let html = format!("<p>{}</p><p>{}</p>", "x".repeat(8 * 1024), "日本語 ünïcödé 🦆".repeat(100));
+        set_body_html(html.clone());
+        assert_eq!(get_document_html(), html);
+
+        set_page_title("🦆".repeat(400));
+        assert_eq!(jse!(-> String, b"return allocateUTF8(document.title);\x00"), "🦆".repeat(400));
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "large results render completely" {
+        let _regions = FakeRegions::install();
+
+        basic_test("select range as n, 'ünïcödé' as s from range(0, 100)");
+
+        let results = jse!(-> String, b"return allocateUTF8(document.getElementById('results').innerHTML);\x00");
+        assert!(results.len() > 4 * 1024);
+        assert!(results.contains("<td>99</td><td>ünïcödé</td>"));
+        assert!(results.ends_with("</div>"));
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "event registry" {
+        use crate::dispatch;
+        use crate::dom::{patch, Region};
+        use crate::events::{self, on, on_key, Event, EventKind};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(vec![]));
+        let recorder = seen.clone();
+        let attribute = on("test-submit", EventKind::Submit, move |event: &Event| {
+            recorder.borrow_mut().push((event.field("query"), event.values("ys")));
+        });
+        assert_eq!(attribute, "Module.dispatch(\"test-submit\", 'submit', this, event)");
+
+        let id = CString::new("test-submit").unwrap();
+        let kind = CString::new("submit").unwrap();
+        let payload = CString::new("query=select+%27%C3%BC%27%3B&ys=1&ys=2").unwrap();
+        assert_eq!(dispatch(id.as_ptr(), kind.as_ptr(), payload.as_ptr()), 1);
+
+        let missing = CString::new("nothing-here").unwrap();
+        assert_eq!(dispatch(missing.as_ptr(), kind.as_ptr(), payload.as_ptr()), 0);
+
+        assert_eq!(
+            *seen.borrow(),
+            vec![(Some("select 'ü';".to_string()), vec!["1".to_string(), "2".to_string()])]
+        );
+
+        // keydowns are only handled when the handler says so
+        let key = on_key("test-key", |event| event.payload == "Ctrl+Enter");
+        assert_eq!(key, "Module.dispatch(\"test-key\", 'keydown', this, event)");
+        let keydown = Event { kind: EventKind::KeyDown, payload: "Ctrl+Enter".to_string() };
+        assert!(events::dispatch("test-key", &keydown));
+        assert!(!events::dispatch("test-key", &Event { payload: "a".to_string(), ..keydown }));
+
+        // a region's handlers go when it's patched again
+        let _regions = FakeRegions::install();
+        patch(Region::Files, String::new());
+        assert!(events::dispatch("test-key", &Event { kind: EventKind::KeyDown, payload: "Ctrl+Enter".to_string() }));
+        on("test-kept", EventKind::Click, |_| {});
+        patch(Region::Files, String::new());
+        assert_eq!(dispatch(id.as_ptr(), kind.as_ptr(), payload.as_ptr()), 0);
+        assert!(!events::dispatch("test-key", &Event { kind: EventKind::KeyDown, payload: "Ctrl+Enter".to_string() }));
+        assert!(events::dispatch("test-kept", &Event { kind: EventKind::Click, payload: String::new() }));
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "promise bridge" {
+        use crate::promise;
+        use crate::settle_promise;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let before = promise::outstanding();
+        let pending = promise::start(|id| jse!(b"global.pendingPromise = $0;\x00", id));
+        let settled = Rc::new(RefCell::new(None));
+        let recorder = settled.clone();
+        promise::spawn(async move {
+            recorder.replace(Some(pending.await));
+        });
+        assert_eq!(*settled.borrow(), None);
+
+        let id = jse!(b"return global.pendingPromise;\x00");
+        let ptr = jse!(b"var ptr = _malloc(4); HEAPU8.set([1, 2, 3, 4], ptr); return ptr;\x00");
+        settle_promise(id, 1, ptr as *mut u8, 4);
+        assert_eq!(*settled.borrow(), Some(Ok(Some(vec![1, 2, 3, 4]))));
+
+        promise::install();
+        let immediate = promise::start(|id| jse!(b"Module.settle($0, UTF8ToString($1, $2));\x00", id, "ünïcödé"));
+        let missing = promise::start(|id| jse!(b"Module.settle($0, null);\x00", id));
+        let recorder = settled.clone();
+        promise::spawn(async move {
+            let text = immediate.await.map(|bytes| String::from_utf8(bytes.unwrap()).unwrap());
+            assert_eq!(text, Ok("ünïcödé".to_string()));
+            recorder.replace(Some(missing.await));
+        });
+        assert_eq!(*settled.borrow(), Some(Ok(None)));
+
+        // dropping a promise before it settles frees it too
+        drop(promise::start(|id| id));
+        assert_eq!(promise::outstanding(), before);
+    }
+
+    test "result encoding" {
+        use crate::data::{decode, encode, ResultData};
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let resolved = conn
+            .query("select 1::tinyint as t, 'ünïcödé' as s, date '2021-03-04' as d, 2.5::double as f, 'ab'::blob as b")
+            .expect("query");
+
+        let decoded = decode(&encode(Ok(&resolved as &dyn ResultData)).expect("encode"))
+            .expect("decode")
+            .expect("result");
+        assert_eq!(decoded.row_count(), 1);
+        for col in 0..resolved.column_count() {
+            assert_eq!(decoded.column_info(col), resolved.column_info(col));
+            assert_eq!(
+                decoded.value(col, 0).expect("value").to_string(),
+                resolved.consume(col, 0).expect("value").to_string()
+            );
+        }
+
+        let error = conn.query("select nope").expect_err("error");
+        let decoded = decode(&encode(Err(&error)).expect("encode")).expect("decode");
+        assert_eq!(decoded.expect_err("error"), error);
+
+        assert!(decode(&[0, 1]).is_err());
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "worker mode" {
+        use crate::rendering::FileBrowser;
+        use render::html;
+
+        main().unwrap();
+        let _regions = FakeRegions::install();
+        jse!(b"
+            // replies synchronously, as if the worker were on this thread
+            Module.worker = {
+                query: function (query) {
+                    return {then: function (resolve) {
+                        global.postMessage = function (message) { resolve(message.reply); };
+                        Module.ccall('worker_query', 'void', ['number', 'string'], [1, query]);
+                    }};
+                },
+                cancel: function () {}
+            };
+        \x00");
+
+        let query = CString::new("select 42 as answer, 'ünïcödé' as s").unwrap();
+        callback(query.as_ptr());
+
+        // uploads land on the page, where the worker can't query them
+        std::fs::write("/worker-mode.csv", "a\n1\n").expect("write");
+        let browser = || html! { <>{FileBrowser { path: std::path::PathBuf::from("/") }}</> };
+        assert!(!browser().contains(">Query</button>"));
+        assert!(browser().contains("can&apos;t see the files"));
+        jse!(b"delete Module.worker; delete global.postMessage;\x00");
+        assert!(browser().contains(">Query</button>"));
+        std::fs::remove_file("/worker-mode.csv").ok();
+
+        let results = jse!(-> String, b"return allocateUTF8(document.getElementById('results').innerHTML);\x00");
+        assert!(results.contains("<td>42</td><td>ünïcödé</td>"));
+        let sidebar = jse!(-> String, b"return allocateUTF8(document.getElementById('sidebar').innerHTML);\x00");
+        assert!(sidebar.contains("Schema"));
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "interrupt errors" {
+        use crate::data::{decode, encode};
+        use crate::error::{ErrorKind, QueryError};
+        use crate::rendering::{ErrorView, Form};
+        use render::html;
+
+        // without a worker there's no cancelling, and the editor says so
+        assert!(html! { <>{Form {}}</> }.contains("can&apos;t be cancelled"));
+
+        let error = QueryError::parse("select count(*) from range(1000000000000)", "INTERRUPT Error: Interrupted!");
+        assert_eq!(error.kind, ErrorKind::Interrupted);
+        assert_eq!(decode(&encode(Err(&error)).expect("encode")).expect("decode").expect_err("error"), error);
+        assert!(html! { <>{ErrorView { error }}</> }.contains("Cancelled"));
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let handle = conn.interrupt_handle();
+        drop(conn);
+        // closed, so there's nothing left to interrupt
+        handle.interrupt();
+    }
+
+    #[cfg(not(target_os = "emscripten"))]
+    test "interrupting a running query" {
+        use crate::error::ErrorKind;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let handle = conn.interrupt_handle();
+        let finished = Arc::new(AtomicBool::new(false));
+
+        // DuckDB forgets an interrupt when the next query starts, so keep
+        // at it until the query is over
+        let interrupter = {
+            let finished = finished.clone();
+            std::thread::spawn(move || {
+                while !finished.load(Ordering::SeqCst) {
+                    std::thread::sleep(Duration::from_millis(10));
+                    handle.interrupt();
+                }
+            })
+        };
+
+        let result = conn.query("select count(*) from range(1000000000) t1, range(1000) t2");
+        finished.store(true, Ordering::SeqCst);
+        interrupter.join().expect("interrupter");
+
+        assert_eq!(result.expect_err("interrupted").kind, ErrorKind::Interrupted);
+        assert!(conn.query("select 1").is_ok());
+    }
+
+    #[cfg(not(target_os = "emscripten"))]
+    test "query timeouts" {
+        use crate::error::ErrorKind;
+        use std::time::Duration;
+
+        let slow = "select count(*) from range(1000000000) t1, range(1000) t2";
+
+        let db = DB::new(None).expect("db");
+        let mut conn = db.connection().expect("connection");
+
+        let error = conn.query_with_timeout(slow, Duration::from_millis(1)).expect_err("timeout");
+        assert_eq!(error.kind, ErrorKind::Timeout);
+        assert_eq!(error.query, slow);
+        assert!(conn.query_with_timeout("select 1", Duration::from_secs(60)).is_ok());
+
+        conn.set_default_timeout(Some(Duration::from_millis(1))).expect("timeout");
+        assert_eq!(conn.query(slow).expect_err("timeout").kind, ErrorKind::Timeout);
+
+        conn.set_default_timeout(None).expect("no timeout");
+        assert!(conn.query("select 1").is_ok());
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "query timeouts" {
+        use crate::error::ErrorKind;
+        use std::time::Duration;
+
+        let db = DB::new(None).expect("db");
+        let mut conn = db.connection().expect("connection");
+
+        // nothing could stop it, so it isn't run at all
+        let error = conn.query_with_timeout("select 1", Duration::from_secs(60)).expect_err("no threads");
+        assert_eq!(error.kind, ErrorKind::Unsupported);
+        assert!(conn.set_default_timeout(Some(Duration::from_secs(60))).is_err());
+        assert!(conn.set_default_timeout(None).is_ok());
+        assert!(conn.query("select 1").is_ok());
+    }
+
+    #[cfg(target_os = "emscripten")]
+    test "multiple databases" {
+        use crate::databases;
+
+        let query = |sql: &str| databases::with_connection(|conn| conn.query(sql).is_ok()).expect("open");
+
+        let first = databases::open(None).expect("open");
+        // the connection lasts, so temporary tables do too
+        assert!(query("create temporary table scratch as select 1 as x"));
+        assert!(query("select x from scratch"));
+
+        let second = databases::open(None).expect("open");
+        assert_ne!(first, second);
+        assert_eq!(databases::current(), Some(second.clone()));
+        assert_eq!(databases::current_path(), None);
+        assert!(!query("select x from scratch"));
+
+        assert!(databases::select(&first));
+        assert!(query("select x from scratch"));
+        assert!(!databases::select("nothing-here"));
+
+        // opening a file again selects it, rather than starting over
+        let file = databases::open(Some("multiple-databases.db")).expect("open");
+        assert!(query("create temporary table kept as select 1 as x"));
+        assert!(databases::select(&first));
+        assert_eq!(databases::open(Some("multiple-databases.db")).expect("open"), file);
+        assert_eq!(databases::current(), Some(file.clone()));
+        assert!(query("select x from kept"));
+        databases::close(&file);
+        std::fs::remove_file("multiple-databases.db").ok();
+        std::fs::remove_file("multiple-databases.db.wal").ok();
+        assert!(databases::select(&first));
+
+        let _regions = FakeRegions::install();
+        crate::render_sidebar(None);
+        let sidebar = jse!(-> String, b"return allocateUTF8(document.getElementById('sidebar').innerHTML);\x00");
+        let options: Vec<&str> = sidebar.split("<option").skip(1).collect();
+        assert!(options.iter().any(|option| option.contains("selected") && option.contains(&format!(">{}</option>", first))));
+        assert!(options.iter().any(|option| !option.contains("selected") && option.contains(&format!(">{}</option>", second))));
+
+        databases::close(&first);
+        databases::close(&second);
+        assert!(!databases::names().contains(&first));
+        assert_ne!(databases::current(), Some(first));
+    }
+
+    test "null values" {
+        use crate::data::{decode, encode, ResultData};
+
+        let db = DB::new(None).expect("db");
+        let conn = db.connection().expect("connection");
+        let result = conn
+            .query("select * from (values (1, null::varchar), (null, 'b')) t(i, s)")
+            .expect("query");
+
+        assert_eq!(result.row_count(), 2);
+        assert!(!result.is_null(0, 0) && result.is_null(1, 0));
+        assert!(result.is_null(0, 1) && !result.is_null(1, 1));
+        assert_eq!(result.consume(1, 1).expect("value").to_string(), "b");
+
+        let decoded = decode(&encode(Ok(&result as &dyn ResultData)).expect("encode"))
+            .expect("decode")
+            .expect("result");
+        assert_eq!(decoded.column_info(1), result.column_info(1));
+        for (col, row) in &[(0, 0), (0, 1), (1, 0), (1, 1)] {
+            assert_eq!(ResultData::is_null(&decoded, *col, *row), result.is_null(*col, *row));
+        }
+    }
+
+    test "connection pooling" {
+        use rust_wasm_duckdb::pool::Pool;
+
+        fn send<T: Send>() {}
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<DB>();
+        send::<crate::db::Connection>();
+        send_sync::<Pool>();
+
+        // the connection keeps the database open
+        let conn = DB::new(None).expect("db").connection().expect("conn");
+        assert!(conn.query("select 42").is_ok());
+        drop(conn);
+
+        let pool = Pool::new(DB::new(None).expect("db"), 2);
+        {
+            let first = pool.get().expect("conn");
+            first.query("create table shared as select 1 as x").expect("create");
+            let second = pool.get().expect("conn");
+            let third = pool.get().expect("conn");
+            assert!(third.query("select x from shared").is_ok());
+            drop((first, second, third));
+        }
+        assert_eq!(pool.idle(), 2);
+        let stale = {
+            let mut reused = pool.get().expect("conn");
+            assert_eq!(pool.idle(), 1);
+            #[cfg(not(target_os = "emscripten"))]
+            reused.set_default_timeout(Some(std::time::Duration::from_secs(1))).expect("timeout");
+            reused.query("begin transaction").expect("begin");
+            reused.query("insert into shared values (2)").expect("insert");
+
+            reused.interrupt_handle()
+        };
+        // given back clean: out of the transaction, without the timeout
+        let reused = pool.get().expect("conn");
+        assert_eq!(reused.default_timeout(), None);
+        let count = reused.query("select count(*) from shared").expect("count");
+        assert!(matches!(count.consume(0, 0), Ok(crate::DbType::Bigint(1))));
+
+        // and out of reach of the last borrower's interrupt handle
+        #[cfg(not(target_os = "emscripten"))]
+        {
+            use std::sync::atomic::{AtomicBool, Ordering};
+            use std::sync::Arc;
+
+            let finished = Arc::new(AtomicBool::new(false));
+            let interrupter = {
+                let finished = finished.clone();
+                std::thread::spawn(move || {
+                    while !finished.load(Ordering::SeqCst) {
+                        stale.interrupt();
+                        std::thread::sleep(std::time::Duration::from_millis(1));
+                    }
+                })
+            };
+            let result = reused.query("select count(*) from range(10000000) t1, range(10) t2");
+            finished.store(true, Ordering::SeqCst);
+            interrupter.join().expect("interrupter");
+            assert!(result.is_ok());
+        }
+        #[cfg(target_os = "emscripten")]
+        drop(stale);
+        drop(reused);
+
+        // sessions that can't be put back are closed instead
+        for changes in &["create temporary table leftover as select 1 as x", "set threads to 1"] {
+            let idle = pool.idle();
+            let changed = pool.get().expect("conn");
+            changed.query(changes).expect("change");
+            drop(changed);
+            assert_eq!(pool.idle(), idle - 1);
+        }
+        assert!(pool.get().expect("conn").query("select x from leftover").is_err());
+
+        #[cfg(not(target_os = "emscripten"))]
+        {
+            let threads: Vec<_> = (0..4)
+                .map(|_| {
+                    let pool = pool.clone();
+                    std::thread::spawn(move || pool.get().expect("conn").query("select x from shared").is_ok())
+                })
+                .collect();
+            assert!(threads.into_iter().all(|thread| thread.join().expect("thread")));
+        }
+    }
 }
@@ -6,6 +6,46 @@ use libc::c_void;
 use std::convert::TryInto;
 use std::fmt::{Display, Error, Formatter};
 
+/// Every physical column type, for turning a discriminant back into one
+pub const ALL_TYPES: [duckdb_type; 15] = [
+    duckdb_type::DUCKDB_TYPE_INVALID,
+    duckdb_type::DUCKDB_TYPE_BOOLEAN,
+    duckdb_type::DUCKDB_TYPE_TINYINT,
+    duckdb_type::DUCKDB_TYPE_SMALLINT,
+    duckdb_type::DUCKDB_TYPE_INTEGER,
+    duckdb_type::DUCKDB_TYPE_BIGINT,
+    duckdb_type::DUCKDB_TYPE_HUGEINT,
+    duckdb_type::DUCKDB_TYPE_FLOAT,
+    duckdb_type::DUCKDB_TYPE_DOUBLE,
+    duckdb_type::DUCKDB_TYPE_TIMESTAMP,
+    duckdb_type::DUCKDB_TYPE_DATE,
+    duckdb_type::DUCKDB_TYPE_TIME,
+    duckdb_type::DUCKDB_TYPE_INTERVAL,
+    duckdb_type::DUCKDB_TYPE_VARCHAR,
+    duckdb_type::DUCKDB_TYPE_BLOB,
+];
+
+/// The SQL name of a physical column type
+pub fn type_name(type_: duckdb_type) -> &'static str {
+    match type_ {
+        duckdb_type::DUCKDB_TYPE_BOOLEAN => "BOOLEAN",
+        duckdb_type::DUCKDB_TYPE_TINYINT => "TINYINT",
+        duckdb_type::DUCKDB_TYPE_SMALLINT => "SMALLINT",
+        duckdb_type::DUCKDB_TYPE_INTEGER => "INTEGER",
+        duckdb_type::DUCKDB_TYPE_BIGINT => "BIGINT",
+        duckdb_type::DUCKDB_TYPE_HUGEINT => "HUGEINT",
+        duckdb_type::DUCKDB_TYPE_FLOAT => "FLOAT",
+        duckdb_type::DUCKDB_TYPE_DOUBLE => "DOUBLE",
+        duckdb_type::DUCKDB_TYPE_TIMESTAMP => "TIMESTAMP",
+        duckdb_type::DUCKDB_TYPE_DATE => "DATE",
+        duckdb_type::DUCKDB_TYPE_TIME => "TIME",
+        duckdb_type::DUCKDB_TYPE_INTERVAL => "INTERVAL",
+        duckdb_type::DUCKDB_TYPE_VARCHAR => "VARCHAR",
+        duckdb_type::DUCKDB_TYPE_BLOB => "BLOB",
+        _ => "UNKNOWN",
+    }
+}
+
 impl Display for duckdb_interval {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         f.debug_struct("duckdb_interval")
@@ -20,7 +60,7 @@ impl From<&duckdb_hugeint> for i128 {
         let sign = if inst.upper >= 0 { 1 } else { -1 };
         let upper = if sign == -1 { -inst.upper } else { inst.upper };
 
-        let mut twisted: i128 = upper.into();
+        let twisted: i128 = upper.into();
         let mut twisted: u128 = twisted.try_into().unwrap();
         twisted <<= 64;
         let step: u128 = inst.lower.into();
@@ -44,16 +84,45 @@ impl Display for duckdb_hugeint {
     }
 }
 
+#[allow(clashing_extern_declarations)]
 extern "C" {
     fn free(ptr: *const c_void);
 }
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct duckdb_blob {
     data: *const c_void,
     pub size: u64,
 }
+impl duckdb_blob {
+    /// Copies `bytes` into a blob that owns them
+    pub fn new(bytes: &[u8]) -> Self {
+        unsafe {
+            let data = libc::malloc(bytes.len().max(1));
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), data as *mut u8, bytes.len());
+
+            Self {
+                data,
+                size: bytes.len() as u64,
+            }
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        if self.data.is_null() {
+            return &[];
+        }
+
+        unsafe { std::slice::from_raw_parts(self.data as *const u8, self.size as usize) }
+    }
+}
+/// Copies the data, as each blob frees its own
+impl Clone for duckdb_blob {
+    fn clone(&self) -> Self {
+        Self::new(self.bytes())
+    }
+}
 impl Drop for duckdb_blob {
     fn drop(&mut self) {
         unsafe {
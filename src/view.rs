@@ -0,0 +1,203 @@
+use crate::data::ResultData;
+use crate::DbType;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sort {
+    pub column: u64,
+    pub direction: Direction,
+}
+
+/// Client side sorting and filtering applied on top of a result. A view
+/// belongs to one result, as it keeps the row order it last worked out
+#[derive(Debug, Clone, Default)]
+pub struct ResultView {
+    pub sort: Option<Sort>,
+    pub filters: BTreeMap<u64, String>,
+    rows: RefCell<Option<Rc<Vec<u64>>>>,
+}
+impl ResultView {
+    /// Sorts ascending by `column`, then descending, then not at all
+    pub fn toggle_sort(&mut self, column: u64) {
+        self.sort = match self.sort {
+            Some(Sort {
+                column: current,
+                direction: Direction::Ascending,
+            }) if current == column => Some(Sort {
+                column,
+                direction: Direction::Descending,
+            }),
+            Some(Sort {
+                column: current,
+                direction: Direction::Descending,
+            }) if current == column => None,
+            _ => Some(Sort {
+                column,
+                direction: Direction::Ascending,
+            }),
+        };
+        self.rows.replace(None);
+    }
+
+    pub fn set_filter(&mut self, column: u64, filter: &str) {
+        if filter.trim().is_empty() {
+            self.filters.remove(&column);
+        } else {
+            self.filters.insert(column, filter.trim().to_string());
+        }
+        self.rows.replace(None);
+    }
+
+    /// Indices of the rows that pass every filter, in sorted order. They're
+    /// only worked out again once the sort or filters change
+    pub fn rows(&self, data: &dyn ResultData) -> Result<Rc<Vec<u64>>, Box<dyn std::error::Error>> {
+        if let Some(rows) = &*self.rows.borrow() {
+            return Ok(rows.clone());
+        }
+
+        let rows = Rc::new(self.filter_and_sort(data)?);
+        self.rows.replace(Some(rows.clone()));
+
+        Ok(rows)
+    }
+
+    fn filter_and_sort(
+        &self,
+        data: &dyn ResultData,
+    ) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        let mut rows = vec![];
+        'rows: for row in 0..data.row_count() {
+            for (column, filter) in &self.filters {
+                // NULLs show as `NULL`, so that's the text a filter sees
+                let passes = if data.is_null(*column, row) {
+                    "null".contains(&filter.to_lowercase())
+                } else {
+                    matches(filter, &data.value(*column, row)?)
+                };
+                if !passes {
+                    continue 'rows;
+                }
+            }
+            rows.push(row);
+        }
+
+        if let Some(sort) = self.sort {
+            let mut keyed = rows
+                .into_iter()
+                .map(|row| {
+                    let value = if data.is_null(sort.column, row) {
+                        None
+                    } else {
+                        Some(data.value(sort.column, row)?)
+                    };
+
+                    Ok((value, row))
+                })
+                .collect::<Result<Vec<(Option<DbType>, u64)>, Box<dyn std::error::Error>>>()?;
+
+            // NULLs go last whichever way the column is sorted
+            keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => match sort.direction {
+                    Direction::Ascending => compare(a, b),
+                    Direction::Descending => compare(b, a),
+                },
+            });
+
+            rows = keyed.into_iter().map(|(_, row)| row).collect();
+        }
+
+        Ok(rows)
+    }
+}
+
+fn compare_floats(a: f64, b: f64) -> Ordering {
+    a.partial_cmp(&b)
+        .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+}
+
+/// Orders two values from the same column by their natural ordering
+pub fn compare(a: &DbType, b: &DbType) -> Ordering {
+    use crate::DbType::*;
+
+    match (a, b) {
+        (Boolean(a), Boolean(b)) => a.cmp(b),
+        (Tinyint(a), Tinyint(b)) => a.cmp(b),
+        (Smallint(a), Smallint(b)) => a.cmp(b),
+        (Integer(a), Integer(b)) => a.cmp(b),
+        (Bigint(a), Bigint(b)) => a.cmp(b),
+        (Hugeint(a), Hugeint(b)) => i128::from(a).cmp(&i128::from(b)),
+        (Float(a), Float(b)) => compare_floats((*a).into(), (*b).into()),
+        (Double(a), Double(b)) => compare_floats(*a, *b),
+        (Date(a), Date(b)) => (a.year, a.month, a.day).cmp(&(b.year, b.month, b.day)),
+        (Time(a), Time(b)) => {
+            (a.hour, a.min, a.sec, a.micros).cmp(&(b.hour, b.min, b.sec, b.micros))
+        }
+        (Timestamp(a), Timestamp(b)) => compare(&Date(a.date), &Date(b.date))
+            .then_with(|| compare(&Time(a.time), &Time(b.time))),
+        (Interval(a), Interval(b)) => {
+            (a.months, a.days, a.micros).cmp(&(b.months, b.days, b.micros))
+        }
+        (String(a), String(b)) => a.cmp(b),
+        (Blob(a), Blob(b)) => a.bytes().cmp(b.bytes()),
+        (Unknown(_), Unknown(_)) => Ordering::Equal,
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+pub fn as_number(value: &DbType) -> Option<f64> {
+    use crate::DbType::*;
+
+    match value {
+        Tinyint(v) => Some((*v).into()),
+        Smallint(v) => Some((*v).into()),
+        Integer(v) => Some((*v).into()),
+        Bigint(v) => Some(*v as f64),
+        Hugeint(v) => Some(i128::from(v) as f64),
+        Float(v) => Some((*v).into()),
+        Double(v) => Some(*v),
+        _ => None,
+    }
+}
+
+/// Numeric columns accept comparisons like `>= 10`, everything else is a
+/// case insensitive substring match
+pub fn matches(filter: &str, value: &DbType) -> bool {
+    if let Some(number) = as_number(value) {
+        use std::cmp::Ordering::*;
+
+        let operators: [(&str, &[Ordering]); 6] = [
+            (">=", &[Greater, Equal]),
+            ("<=", &[Less, Equal]),
+            ("!=", &[Less, Greater]),
+            (">", &[Greater]),
+            ("<", &[Less]),
+            ("=", &[Equal]),
+        ];
+
+        for (operator, accepted) in &operators {
+            if let Some(operand) = filter.strip_prefix(operator) {
+                return match operand.trim().parse::<f64>() {
+                    Ok(operand) => accepted.contains(&compare_floats(number, operand)),
+                    Err(_) => false,
+                };
+            }
+        }
+    }
+
+    value
+        .to_string()
+        .to_lowercase()
+        .contains(&filter.to_lowercase())
+}
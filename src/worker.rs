@@ -0,0 +1,184 @@
+use crate::catalog::{Catalog, COLUMNS_QUERY, FUNCTIONS_QUERY, TABLES_QUERY};
+use crate::data::{self, OwnedResult, ResultData};
+use crate::error::{ErrorKind, QueryError};
+use crate::jse;
+use crate::promise;
+
+/// Whether this copy of the module was started by `start`, rather than by
+/// the page
+pub fn is_worker() -> bool {
+    jse!(-> bool, b"return typeof importScripts === 'function' && typeof document === 'undefined';\x00")
+}
+
+/// Whether the page asked for worker mode, with `?worker` in its url
+pub fn requested() -> bool {
+    jse!(
+        -> bool,
+        b"
+        return typeof Worker !== 'undefined'
+            && typeof location !== 'undefined'
+            && new URLSearchParams(location.search).has('worker');
+    \x00"
+    )
+}
+
+/// Whether queries go to the worker. The database then lives only in the
+/// worker, so files uploaded to the page aren't visible to queries
+pub fn enabled() -> bool {
+    jse!(-> bool, b"return !!Module.worker;\x00")
+}
+
+/// What the queries `cancel` gives up on are rejected with
+const CANCELLED: &str = "Cancelled";
+
+/// Defines `Module.worker` and starts a worker running this same script.
+/// Queries sent before it's opened the database wait in a queue, and
+/// each reply is `data::encode`d
+pub fn start() -> i32 {
+    jse!(
+        b"
+        var cancelled = UTF8ToString($0, $1);
+        Module.worker = {
+            next: 1,
+            pending: {},
+            spawn: function () {
+                var self = this;
+                self.ready = false;
+                self.queue = [];
+                // the same script index.html loads
+                self.current = new Worker('/bin.js');
+                self.current.onmessage = function (event) {
+                    if (event.data.ready) {
+                        self.ready = true;
+                        self.queue.forEach(function (message) { self.current.postMessage(message); });
+                        self.queue = [];
+                        return;
+                    }
+
+                    var pending = self.pending[event.data.id];
+                    delete self.pending[event.data.id];
+                    if (pending) { pending.resolve(event.data.reply); }
+                };
+                self.current.onerror = function (event) {
+                    self.fail(new Error(event.message || 'Worker failed'));
+                };
+            },
+            query: function (query) {
+                var self = this;
+                var id = self.next++;
+
+                return new Promise(function (resolve, reject) {
+                    self.pending[id] = {resolve: resolve, reject: reject};
+
+                    var message = {id: id, query: query};
+                    if (self.ready) {
+                        self.current.postMessage(message);
+                    } else {
+                        self.queue.push(message);
+                    }
+                });
+            },
+            fail: function (error) {
+                var pending = this.pending;
+                this.pending = {};
+                Object.keys(pending).forEach(function (id) { pending[id].reject(error); });
+            },
+            cancel: function () {
+                if (this.ready) {
+                    this.current.postMessage({interrupt: true});
+                }
+                // replies to queries given up on are dropped when they come
+                this.fail(new Error(cancelled));
+            }
+        };
+        Module.worker.spawn();
+    \x00",
+        CANCELLED
+    )
+}
+
+/// Runs `query` in the worker
+pub async fn query(query: &str) -> Result<OwnedResult, QueryError> {
+    let reply = promise::start(|id| {
+        jse!(
+            b"Module.settle($0, Module.worker.query(UTF8ToString($1, $2)));\x00",
+            id,
+            query
+        )
+    })
+    .await;
+
+    match reply {
+        Ok(Some(bytes)) => data::decode(&bytes).unwrap_or_else(|error| {
+            Err(QueryError::new(
+                query,
+                ErrorKind::Unknown,
+                &error.to_string(),
+            ))
+        }),
+        Ok(None) => Err(QueryError::new(
+            query,
+            ErrorKind::Unknown,
+            "The worker didn't reply",
+        )),
+        Err(message) if message == CANCELLED => Err(QueryError::new(
+            query,
+            ErrorKind::Interrupted,
+            "Interrupted!",
+        )),
+        Err(message) => Err(QueryError::new(query, ErrorKind::Unknown, &message)),
+    }
+}
+
+/// Stops waiting on the worker's queries, and asks it to interrupt the one
+/// it's running. Its databases are kept, but its only thread is busy with
+/// that query, so the message only gets through once DuckDB returns
+pub fn cancel() -> i32 {
+    jse!(b"if (Module.worker) { Module.worker.cancel(); }\x00")
+}
+
+/// Loads the catalog through the worker
+pub async fn catalog() -> Result<Catalog, Box<dyn std::error::Error>> {
+    let tables = query(TABLES_QUERY).await?;
+    let columns = query(COLUMNS_QUERY).await?;
+    let functions = query(FUNCTIONS_QUERY).await;
+
+    Catalog::from_results(
+        &tables,
+        &columns,
+        functions.as_ref().ok().map(|f| f as &dyn ResultData),
+    )
+}
+
+/// In the worker, passes each query the page sends to `worker_query`, and
+/// each cancel to `worker_interrupt`
+pub fn install_host() -> i32 {
+    jse!(
+        b"
+        onmessage = function (event) {
+            if (event.data.interrupt) {
+                Module.ccall('worker_interrupt', 'void', [], []);
+                return;
+            }
+            Module.ccall('worker_query', 'void', ['number', 'string'], [event.data.id, event.data.query]);
+        };
+    \x00"
+    )
+}
+
+/// Lets the page know the database is open, so queued queries can be sent
+pub fn ready() -> i32 {
+    jse!(b"postMessage({ready: true});\x00")
+}
+
+/// Sends the `data::encode`d reply to query `id` back to the page
+pub fn reply(id: i32, reply: &[u8]) -> i32 {
+    jse!(
+        b"
+        var reply = HEAPU8.slice($1, $1 + $2);
+        postMessage({id: $0, reply: reply}, [reply.buffer]);
+    \x00",
+        id,
+        reply
+    )
+}